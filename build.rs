@@ -0,0 +1,44 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn walk(dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			walk(&path, files)?;
+		} else {
+			files.push(path.to_string_lossy().replace('\\', "/"));
+		}
+	}
+
+	Ok(())
+}
+
+fn main() {
+	println!("cargo::rerun-if-changed=templates/");
+	if env::var_os("CARGO_FEATURE_EMBED").is_none() {
+		return;
+	}
+
+	let root =
+		Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("templates");
+	let mut files = Vec::new();
+	walk(&root, &mut files).expect("failed to read templates");
+	files.sort();
+
+	let root = root.to_string_lossy().replace('\\', "/");
+	let mut templates = String::from("&[\n");
+	for file in files {
+		let relative_path =
+			file.strip_prefix(&root).unwrap().trim_start_matches('/');
+		templates
+			.push_str(&format!("\t({relative_path:?}, include_str!({file:?})),\n"));
+	}
+	templates.push(']');
+
+	let out_dir = env::var("OUT_DIR").unwrap();
+	fs::write(Path::new(&out_dir).join("templates.rs"), templates)
+		.expect("failed to write embedded templates");
+}
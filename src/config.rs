@@ -1,18 +1,122 @@
 use etcetera::BaseStrategy;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
+use serde::de;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io;
-use std::io::IsTerminal;
-use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::prompt;
+use crate::value::Value;
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Config {
+	/// Where to fetch templates from, in order of priority. Can be written as
+	/// either a single source, or a list of them.
+	#[serde(
+		default,
+		deserialize_with = "one_or_many",
+		skip_serializing_if = "Vec::is_empty"
+	)]
+	pub source: Vec<SourceConfig>,
 	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
-	pub context: HashMap<String, String>,
+	pub context: HashMap<String, Value>,
+	/// Rules for deriving variables from others, like
+	/// `"repo.url" = "https://github.com/{{ github.owner }}/{{ name }}"`
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub derive: BTreeMap<String, String>,
+	/// Fail, rather than warn, when a template uses a variable that isn't set
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub strict: bool,
+}
+
+/// A source of templates, written as either just a URL or path, or as a table
+/// with extra options. If `url` is unset, the default source is used.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(remote = "Self", deny_unknown_fields)]
+pub struct SourceConfig {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+	/// A branch, tag, or commit to fetch templates from
+	#[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+	pub git_ref: Option<String>,
+	/// The directory inside of a repository which contains the templates
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub path: Option<String>,
+	/// Extra headers to send with every request to an HTTP source
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub headers: BTreeMap<String, String>,
+	/// A bearer token to authenticate with an HTTP source. Prefer `token_env`,
+	/// so that the token doesn't need to be stored in the config file.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token: Option<String>,
+	/// The name of an environment variable to read the token from
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token_env: Option<String>,
+}
+
+impl From<String> for SourceConfig {
+	fn from(url: String) -> Self {
+		SourceConfig { url: Some(url), ..Default::default() }
+	}
+}
+
+impl<'de> Deserialize<'de> for SourceConfig {
+	fn deserialize<D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> de::Visitor<'de> for Visitor {
+			type Value = SourceConfig;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a URL, a path, or a table")
+			}
+
+			fn visit_str<E: de::Error>(self, url: &str) -> Result<Self::Value, E> {
+				Ok(SourceConfig::from(url.to_string()))
+			}
+
+			fn visit_map<M: de::MapAccess<'de>>(
+				self,
+				map: M,
+			) -> Result<Self::Value, M::Error> {
+				SourceConfig::deserialize(de::value::MapAccessDeserializer::new(map))
+			}
+		}
+
+		deserializer.deserialize_any(Visitor)
+	}
+}
+
+impl Serialize for SourceConfig {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		SourceConfig::serialize(self, serializer)
+	}
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum OneOrMany<T> {
+		One(T),
+		Many(Vec<T>),
+	}
+
+	Ok(match OneOrMany::deserialize(deserializer)? {
+		OneOrMany::One(one) => vec![one],
+		OneOrMany::Many(many) => many,
+	})
 }
 
 impl Config {
@@ -20,18 +124,16 @@ impl Config {
 		Ok(etcetera::choose_base_strategy()?.config_dir().join("bai.toml"))
 	}
 
+	pub fn cache_dir() -> anyhow::Result<PathBuf> {
+		Ok(etcetera::choose_base_strategy()?.cache_dir().join("bai"))
+	}
+
 	pub fn init() -> anyhow::Result<Self> {
-		let stdin = io::stdin();
-		if !stdin.is_terminal() {
+		if !prompt::is_interactive() {
 			return Ok(Default::default());
 		}
 
-		let mut buf = String::with_capacity(100);
-		let mut stdout = io::stdout();
-		write!(stdout, "github.username? ")?;
-		stdout.flush().unwrap();
-		stdin.read_line(&mut buf).unwrap();
-		let github_username = buf.trim().to_string();
+		let github_username = prompt::ask("github.username?")?;
 
 		if !github_username.is_empty() {
 			Self::set_context([("github.username", &github_username)])
@@ -41,8 +143,9 @@ impl Config {
 		Ok(Config {
 			context: HashMap::from([(
 				"github.username".to_string(),
-				github_username,
+				github_username.into(),
 			)]),
+			..Default::default()
 		})
 	}
 
@@ -85,3 +188,71 @@ impl Config {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn url(url: &str) -> SourceConfig {
+		SourceConfig::from(url.to_string())
+	}
+
+	#[test]
+	fn parse_sources() {
+		let config: Config = toml::from_str(r#"source = "./overrides/""#).unwrap();
+		assert_eq!(config.source, [url("./overrides/")]);
+
+		let config: Config =
+			toml::from_str(r#"source = ["./overrides/", "https://example.com/"]"#)
+				.unwrap();
+		assert_eq!(
+			config.source,
+			[url("./overrides/"), url("https://example.com/")]
+		);
+
+		let config: Config = toml::from_str(r#"source.ref = "v1.0.3""#).unwrap();
+		assert_eq!(
+			config.source,
+			[SourceConfig {
+				git_ref: Some("v1.0.3".to_string()),
+				..Default::default()
+			}],
+		);
+
+		let config: Config = toml::from_str(
+			r#"source = ["./overrides/", { url = "https://example.com/", ref = "abc" }]"#,
+		)
+		.unwrap();
+		assert_eq!(
+			config.source,
+			[
+				url("./overrides/"),
+				SourceConfig {
+					url: Some("https://example.com/".to_string()),
+					git_ref: Some("abc".to_string()),
+					..Default::default()
+				},
+			],
+		);
+
+		let config: Config = toml::from_str("").unwrap();
+		assert!(config.source.is_empty());
+	}
+
+	#[test]
+	fn parse_context() {
+		let config: Config = toml::from_str(
+			r#"
+			[context]
+			"author.name" = "James Baxter"
+			keywords = ["cli", "templates"]
+			"#,
+		)
+		.unwrap();
+		assert_eq!(config.context["author.name"], Value::from("James Baxter"));
+		assert_eq!(
+			config.context["keywords"],
+			Value::from(vec!["cli".to_string(), "templates".to_string()]),
+		);
+	}
+}
@@ -0,0 +1,132 @@
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+
+use crate::Context;
+use crate::template;
+use crate::template::Partials;
+use crate::template::Template;
+
+/// Variables which are derived from others by default. Each of these can be
+/// replaced in the `[derive]` table of the config file.
+static DEFAULT_RULES: &[(&str, &str)] = &[
+	("github.owner", "{{ github.username }}"),
+	("author.homepage", "{{ github.owner }}.github.io"),
+	("license.owner", "{{ licence.owner ?? author.name }}"),
+	("licence.owner", "{{ license.owner }}"),
+];
+
+struct Rule<'a> {
+	template: Template<'a>,
+	required: Vec<&'a str>,
+	optional: Vec<&'a str>,
+}
+
+/// Sets any variables which can be derived from others, using the default rules
+/// along with those from the config file. Variables which are already set are
+/// left alone, and rules which use variables that aren't set are skipped.
+///
+/// Rules are evaluated once every rule which sets a variable they use has been.
+/// If rules use each other's variables, those which can do without (because
+/// they have a default) go first, and a cycle of rules which can't is an error.
+pub fn derive(
+	ctx: &mut Context,
+	rules: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+	let mut pending = DEFAULT_RULES
+		.iter()
+		.map(|(name, rule)| (*name, *rule))
+		.filter(|(name, _)| !rules.contains_key(*name))
+		.chain(rules.iter().map(|(name, rule)| (name.as_str(), rule.as_str())))
+		.filter(|(name, _)| !ctx.contains_key(*name))
+		.map(|(name, rule)| {
+			let template = template::parse(rule)
+				.map_err(|err| anyhow!("invalid rule for {}: {}", name, err))?;
+			let (required, optional) = template.variables();
+			Ok((name, Rule { template, required, optional }))
+		})
+		.collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+	while !pending.is_empty() {
+		let waits_on =
+			|names: &[&str]| names.iter().any(|n| pending.contains_key(n));
+		let next = pending
+			.iter()
+			.find(|(_, rule)| !waits_on(&rule.required) && !waits_on(&rule.optional))
+			.or_else(|| pending.iter().find(|(_, rule)| !waits_on(&rule.required)))
+			.map(|(name, _)| *name)
+			.ok_or_else(|| {
+				anyhow!(
+					"derived variables depend on each other in a cycle: {}",
+					pending.keys().copied().collect::<Vec<_>>().join(", ")
+				)
+			})?;
+
+		let rule = pending.remove(next).unwrap();
+		let rendered = rule.template.render(ctx, &Partials::new());
+		if rendered.unresolved.is_empty() {
+			ctx.insert(next.to_string(), rendered.text.into());
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::value::Value;
+
+	fn context(values: &[(&str, &str)]) -> Context {
+		values
+			.iter()
+			.map(|(key, value)| (key.to_string(), Value::from(*value)))
+			.collect()
+	}
+
+	fn rule_map(rules: &[(&str, &str)]) -> BTreeMap<String, String> {
+		rules
+			.iter()
+			.map(|(key, rule)| (key.to_string(), rule.to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn default_rules() {
+		let mut ctx =
+			context(&[("github.username", "aslilac"), ("author.name", "McKayla")]);
+		derive(&mut ctx, &BTreeMap::new()).unwrap();
+		assert_eq!(ctx["github.owner"], Value::from("aslilac"));
+		assert_eq!(ctx["author.homepage"], Value::from("aslilac.github.io"));
+		assert_eq!(ctx["license.owner"], Value::from("McKayla"));
+		assert_eq!(ctx["licence.owner"], Value::from("McKayla"));
+
+		let mut ctx =
+			context(&[("licence.owner", "Someone"), ("author.name", "McKayla")]);
+		derive(&mut ctx, &BTreeMap::new()).unwrap();
+		assert_eq!(ctx["license.owner"], Value::from("Someone"));
+		assert!(!ctx.contains_key("github.owner"));
+
+		let mut ctx = context(&[("license.owner", "Someone")]);
+		derive(&mut ctx, &BTreeMap::new()).unwrap();
+		assert_eq!(ctx["licence.owner"], Value::from("Someone"));
+	}
+
+	#[test]
+	fn custom_rules() {
+		let mut ctx = context(&[("github.username", "aslilac"), ("name", "bai")]);
+		let rules = rule_map(&[
+			("repo.url", "https://github.com/{{ github.owner }}/{{ name }}"),
+			("author.homepage", "{{ github.owner }}.dev"),
+		]);
+		derive(&mut ctx, &rules).unwrap();
+		assert_eq!(ctx["repo.url"], Value::from("https://github.com/aslilac/bai"));
+		assert_eq!(ctx["author.homepage"], Value::from("aslilac.dev"));
+
+		let cycle = rule_map(&[("a", "{{ b }}"), ("b", "{{ a }}")]);
+		assert!(derive(&mut context(&[]), &cycle).is_err());
+		// The cycle doesn't matter if one of the variables is already set
+		let mut ctx = context(&[("a", "1")]);
+		derive(&mut ctx, &cycle).unwrap();
+		assert_eq!(ctx["b"], Value::from("1"));
+	}
+}
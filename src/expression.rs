@@ -0,0 +1,407 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::IDENT;
+use crate::filters::Filter;
+use crate::value::Value;
+
+static IDENTIFIER: Lazy<Regex> =
+	Lazy::new(|| Regex::new(&format!("^{}", *IDENT)).unwrap());
+static LOOP_VARIABLE: Lazy<Regex> =
+	Lazy::new(|| Regex::new("^@(index|first|last)").unwrap());
+
+/// The expressions which can be used inside of a tag. From lowest to highest
+/// precedence:
+///
+/// - `expression | filter`, which applies a filter to everything before it
+/// - `a == b` and `a != b`, which are `"true"` or `"false"`
+/// - `a ?? b`, which is `a` if it's set, and `b` otherwise
+/// - `a + b`, which joins two strings together
+/// - variables, `"strings"`, and `(parentheses)`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression<'a> {
+	Variable(&'a str),
+	Literal(&'a str),
+	Concat(Vec<Expression<'a>>),
+	Coalesce(Vec<Expression<'a>>),
+	Compare {
+		negated: bool,
+		left: Box<Expression<'a>>,
+		right: Box<Expression<'a>>,
+	},
+	Filter(Box<Expression<'a>>, Filter),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+	Variable(&'a str),
+	Literal(&'a str),
+	Plus,
+	Coalesce,
+	Equal,
+	NotEqual,
+	Pipe,
+	OpenParen,
+	CloseParen,
+}
+
+/// Splits an expression into tokens, or returns `None` if it contains anything
+/// that isn't part of the language.
+fn tokenize(text: &str) -> Option<Vec<Token<'_>>> {
+	let mut tokens = Vec::new();
+	let mut rest = text.trim_start_matches(' ');
+
+	while !rest.is_empty() {
+		let (token, len) = if let Some(name) =
+			IDENTIFIER.find(rest).or_else(|| LOOP_VARIABLE.find(rest))
+		{
+			(Token::Variable(name.as_str()), name.len())
+		} else if let Some(literal) = rest.strip_prefix('"') {
+			let end = literal.find('"')?;
+			(Token::Literal(&literal[..end]), end + 2)
+		} else {
+			[
+				("??", Token::Coalesce),
+				("==", Token::Equal),
+				("!=", Token::NotEqual),
+				("+", Token::Plus),
+				("|", Token::Pipe),
+				("(", Token::OpenParen),
+				(")", Token::CloseParen),
+			]
+			.into_iter()
+			.find(|(symbol, _)| rest.starts_with(symbol))
+			.map(|(symbol, token)| (token, symbol.len()))?
+		};
+
+		tokens.push(token);
+		rest = rest[len..].trim_start_matches(' ');
+	}
+
+	Some(tokens)
+}
+
+struct Parser<'a> {
+	tokens: Vec<Token<'a>>,
+	i: usize,
+}
+
+/// The result of parsing something that might not be an expression at all, in
+/// which case it's `Ok(None)`.
+type ParseResult<'a> = anyhow::Result<Option<Expression<'a>>>;
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<Token<'a>> {
+		self.tokens.get(self.i).copied()
+	}
+
+	fn next_if(&mut self, token: Token) -> bool {
+		let matches = self.peek() == Some(token);
+		if matches {
+			self.i += 1;
+		}
+		matches
+	}
+
+	fn filtered(&mut self) -> ParseResult<'a> {
+		let Some(mut expression) = self.comparison()? else {
+			return Ok(None);
+		};
+		while self.next_if(Token::Pipe) {
+			let Some(Token::Variable(name)) = self.peek() else {
+				return Ok(None);
+			};
+			// An unknown filter probably belongs to some other template language
+			let Ok(filter) = name.parse() else {
+				return Ok(None);
+			};
+			self.i += 1;
+			expression = Expression::Filter(Box::new(expression), filter);
+		}
+		Ok(Some(expression))
+	}
+
+	fn comparison(&mut self) -> ParseResult<'a> {
+		let Some(left) = self.coalesce()? else {
+			return Ok(None);
+		};
+		let negated = match self.peek() {
+			Some(Token::Equal) => false,
+			Some(Token::NotEqual) => true,
+			_ => return Ok(Some(left)),
+		};
+		self.i += 1;
+		let Some(right) = self.coalesce()? else {
+			return Ok(None);
+		};
+		Ok(Some(Expression::Compare {
+			negated,
+			left: Box::new(left),
+			right: Box::new(right),
+		}))
+	}
+
+	fn coalesce(&mut self) -> ParseResult<'a> {
+		self.list(Token::Coalesce, Self::concat, Expression::Coalesce)
+	}
+
+	fn concat(&mut self) -> ParseResult<'a> {
+		self.list(Token::Plus, Self::primary, Expression::Concat)
+	}
+
+	/// Parses one or more of whatever `operand` parses, separated by `separator`
+	fn list(
+		&mut self,
+		separator: Token,
+		operand: fn(&mut Self) -> ParseResult<'a>,
+		combine: fn(Vec<Expression<'a>>) -> Expression<'a>,
+	) -> ParseResult<'a> {
+		let mut operands = Vec::new();
+		loop {
+			let Some(expression) = operand(self)? else {
+				return Ok(None);
+			};
+			operands.push(expression);
+			if !self.next_if(separator) {
+				break;
+			}
+		}
+
+		if operands.len() == 1 {
+			return Ok(operands.pop());
+		}
+		Ok(Some(combine(operands)))
+	}
+
+	fn primary(&mut self) -> ParseResult<'a> {
+		let Some(token) = self.peek() else {
+			return Ok(None);
+		};
+		self.i += 1;
+		match token {
+			Token::Variable(name) => Ok(Some(Expression::Variable(name))),
+			Token::Literal(text) => Ok(Some(Expression::Literal(text))),
+			Token::OpenParen => {
+				let expression = self.filtered()?;
+				if !self.next_if(Token::CloseParen) {
+					return Ok(None);
+				}
+				Ok(expression)
+			}
+			_ => Ok(None),
+		}
+	}
+}
+
+/// Parses the inside of a tag as an expression. Returns `Ok(None)` if it isn't
+/// one, so that things like GitHub Actions expressions, or Jinja tags with
+/// filters that we don't have, can be left alone.
+pub fn parse(text: &str) -> ParseResult<'_> {
+	let Some(tokens) = tokenize(text) else {
+		return Ok(None);
+	};
+	let mut parser = Parser { tokens, i: 0 };
+	let expression = parser.filtered()?;
+	if parser.i < parser.tokens.len() {
+		return Ok(None);
+	}
+	Ok(expression)
+}
+
+impl<'a> Expression<'a> {
+	/// Evaluates the expression, using `get` to look up variables. If the
+	/// expression can't be evaluated because of variables that aren't set,
+	/// returns the names of those variables.
+	pub fn eval<F>(&self, get: &F) -> Result<Value, Vec<&'a str>>
+	where
+		F: Fn(&str) -> Option<Value>,
+	{
+		match self {
+			Expression::Variable(name) => get(name).ok_or_else(|| vec![*name]),
+			Expression::Literal(text) => Ok(Value::from(*text)),
+			Expression::Concat(operands) => {
+				let mut text = String::new();
+				let mut missing = Vec::new();
+				for operand in operands {
+					match operand.eval(get) {
+						Ok(value) => text.push_str(&value.to_string()),
+						Err(names) => missing.extend(names),
+					}
+				}
+				if !missing.is_empty() {
+					return Err(missing);
+				}
+				Ok(Value::String(text))
+			}
+			Expression::Coalesce(operands) => {
+				// If none of them are set, it's the first one that's missing
+				let mut missing = None;
+				for operand in operands {
+					match operand.eval(get) {
+						Ok(value) => return Ok(value),
+						Err(names) => {
+							missing.get_or_insert(names);
+						}
+					}
+				}
+				Err(missing.unwrap_or_default())
+			}
+			Expression::Compare { negated, left, right } => {
+				// Variables which aren't set are only equal to each other
+				let equal = left.eval(get).ok() == right.eval(get).ok();
+				Ok(Value::String((equal != *negated).to_string()))
+			}
+			Expression::Filter(expression, filter) => {
+				Ok(filter.apply_value(&expression.eval(get)?))
+			}
+		}
+	}
+
+	/// Collects the variables used by the expression, as those which need to be
+	/// set for it to be evaluated, and those which it can do without.
+	pub fn variables(
+		&self,
+		required: &mut Vec<&'a str>,
+		optional: &mut Vec<&'a str>,
+	) {
+		match self {
+			Expression::Variable(name) => required.push(name),
+			Expression::Literal(_) => (),
+			Expression::Concat(operands) => {
+				for operand in operands {
+					operand.variables(required, optional);
+				}
+			}
+			Expression::Coalesce(operands) => {
+				let (last, rest) = operands.split_last().unwrap();
+				for operand in rest {
+					operand.optional_variables(optional);
+				}
+				last.variables(required, optional);
+			}
+			Expression::Compare { left, right, .. } => {
+				left.optional_variables(optional);
+				right.optional_variables(optional);
+			}
+			Expression::Filter(expression, _) => {
+				expression.variables(required, optional)
+			}
+		}
+	}
+
+	/// Collects every variable used by the expression as optional, for when the
+	/// whole expression is.
+	pub fn optional_variables(&self, optional: &mut Vec<&'a str>) {
+		let mut required = Vec::new();
+		self.variables(&mut required, optional);
+		optional.extend(required);
+	}
+
+	/// Collects variables which fall back to some text when they aren't set, as
+	/// in `name ?? "default"`, along with that text.
+	pub fn defaults(&self, defaults: &mut Vec<(&'a str, &'a str)>) {
+		match self {
+			Expression::Variable(_) | Expression::Literal(_) => (),
+			Expression::Concat(operands) => {
+				for operand in operands {
+					operand.defaults(defaults);
+				}
+			}
+			Expression::Coalesce(operands) => {
+				if let Some(Expression::Literal(text)) = operands.last() {
+					for operand in operands {
+						if let Expression::Variable(name) = operand {
+							defaults.push((name, text));
+						}
+					}
+				}
+			}
+			Expression::Compare { left, right, .. } => {
+				left.defaults(defaults);
+				right.defaults(defaults);
+			}
+			Expression::Filter(expression, _) => expression.defaults(defaults),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_expressions() {
+		assert_eq!(parse("name").unwrap(), Some(Expression::Variable("name")));
+		assert_eq!(
+			parse(r#"github.owner + "/" + name"#).unwrap(),
+			Some(Expression::Concat(vec![
+				Expression::Variable("github.owner"),
+				Expression::Literal("/"),
+				Expression::Variable("name"),
+			])),
+		);
+		assert_eq!(
+			parse(r#"a ?? "b" | upper"#).unwrap(),
+			Some(Expression::Filter(
+				Box::new(Expression::Coalesce(vec![
+					Expression::Variable("a"),
+					Expression::Literal("b"),
+				])),
+				Filter::Upper,
+			)),
+		);
+		assert_eq!(
+			parse(r#"license != "MIT""#).unwrap(),
+			Some(Expression::Compare {
+				negated: true,
+				left: Box::new(Expression::Variable("license")),
+				right: Box::new(Expression::Literal("MIT")),
+			}),
+		);
+		assert!(parse("(name | snake) + \"_test\"").unwrap().is_some());
+
+		// Things that aren't expressions, like GitHub Actions expressions
+		assert_eq!(parse("github.event_name == 'pull_request'").unwrap(), None);
+		assert_eq!(parse("github.head_ref || github.ref").unwrap(), None);
+		assert_eq!(parse("!startsWith(matrix.os, 'windows')").unwrap(), None);
+		assert_eq!(parse("a +").unwrap(), None);
+		assert_eq!(parse("(a").unwrap(), None);
+		assert_eq!(parse("a b").unwrap(), None);
+		assert_eq!(parse("").unwrap(), None);
+		assert_eq!(parse("name | reverse").unwrap(), None);
+		assert_eq!(parse("item | default").unwrap(), None);
+	}
+
+	#[test]
+	fn eval_expressions() {
+		let get = |name: &str| match name {
+			"name" => Some(Value::from("my-project")),
+			"license" => Some(Value::from("MIT")),
+			_ => None,
+		};
+		let eval = |text| parse(text).unwrap().unwrap().eval(&get);
+
+		assert_eq!(eval(r#""@me/" + name"#), Ok(Value::from("@me/my-project")));
+		assert_eq!(
+			eval(r#"(name | snake) + "_test""#),
+			Ok(Value::from("my_project_test"))
+		);
+		assert_eq!(eval(r#"license == "MIT""#), Ok(Value::from("true")));
+		assert_eq!(eval(r#"owner == "MIT""#), Ok(Value::from("false")));
+		assert_eq!(eval(r#"owner + "/" + repo"#), Err(vec!["owner", "repo"]));
+		assert_eq!(eval(r#"owner ?? repo"#), Err(vec!["owner"]));
+		assert_eq!(eval(r#"owner ?? name | upper"#), Ok(Value::from("MY-PROJECT")));
+	}
+
+	#[test]
+	fn expression_defaults() {
+		let defaults = |text| {
+			let mut defaults = Vec::new();
+			parse(text).unwrap().unwrap().defaults(&mut defaults);
+			defaults
+		};
+		assert_eq!(defaults(r#"a ?? b ?? "c" | upper"#), [("a", "c"), ("b", "c")]);
+		assert_eq!(defaults(r#"(a ?? "b") + (c ?? d)"#), [("a", "b")]);
+		assert!(defaults("a").is_empty());
+	}
+}
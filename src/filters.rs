@@ -0,0 +1,143 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+
+use crate::value::Value;
+
+/// A transformation applied to a variable before it's filled in, written as
+/// `{{ name | snake }}` in templates, or `$$name|snake$$` in paths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+	Snake,
+	Kebab,
+	Pascal,
+	Camel,
+	Upper,
+	Lower,
+	Title,
+}
+
+impl FromStr for Filter {
+	type Err = anyhow::Error;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Ok(match name {
+			"snake" => Filter::Snake,
+			"kebab" => Filter::Kebab,
+			"pascal" => Filter::Pascal,
+			"camel" => Filter::Camel,
+			"upper" => Filter::Upper,
+			"lower" => Filter::Lower,
+			"title" => Filter::Title,
+			_ => return Err(anyhow!("unknown filter \"{}\"", name)),
+		})
+	}
+}
+
+/// Splits a name into words, at anything that isn't a letter or number, and
+/// where the case changes, so that `myHTTPServer` becomes `my`, `HTTP`, and
+/// `Server`.
+fn words(text: &str) -> Vec<String> {
+	let chars = text.chars().collect::<Vec<_>>();
+	let mut words = Vec::new();
+	let mut word = String::new();
+
+	for (i, &c) in chars.iter().enumerate() {
+		if !c.is_alphanumeric() {
+			if !word.is_empty() {
+				words.push(std::mem::take(&mut word));
+			}
+			continue;
+		}
+
+		if c.is_uppercase() && !word.is_empty() {
+			let previous = chars[i - 1];
+			let next_is_lowercase =
+				chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+			if !previous.is_uppercase() || next_is_lowercase {
+				words.push(std::mem::take(&mut word));
+			}
+		}
+		word.push(c);
+	}
+
+	if !word.is_empty() {
+		words.push(word);
+	}
+	words
+}
+
+fn capitalize(word: &str) -> String {
+	let mut chars = word.chars();
+	match chars.next() {
+		Some(first) => {
+			first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect()
+		}
+		None => String::new(),
+	}
+}
+
+impl Filter {
+	pub fn apply(self, text: &str) -> String {
+		let words = || words(text).into_iter();
+		match self {
+			Filter::Snake => {
+				words().map(|word| word.to_lowercase()).collect::<Vec<_>>().join("_")
+			}
+			Filter::Kebab => {
+				words().map(|word| word.to_lowercase()).collect::<Vec<_>>().join("-")
+			}
+			Filter::Pascal => words().map(|word| capitalize(&word)).collect(),
+			Filter::Camel => {
+				let mut words = words();
+				let first = words.next().unwrap_or_default().to_lowercase();
+				first + &words.map(|word| capitalize(&word)).collect::<String>()
+			}
+			Filter::Upper => text.to_uppercase(),
+			Filter::Lower => text.to_lowercase(),
+			Filter::Title => {
+				words().map(|word| capitalize(&word)).collect::<Vec<_>>().join(" ")
+			}
+		}
+	}
+
+	/// Applies the filter to every item of a list, or to a string.
+	pub fn apply_value(self, value: &Value) -> Value {
+		match value {
+			Value::String(text) => Value::String(self.apply(text)),
+			Value::List(items) => {
+				Value::List(items.iter().map(|item| self.apply(item)).collect())
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn split_words() {
+		assert_eq!(words("my_project"), ["my", "project"]);
+		assert_eq!(words("my-cool project"), ["my", "cool", "project"]);
+		assert_eq!(words("myHTTPServer"), ["my", "HTTP", "Server"]);
+		assert_eq!(words("MyProject2"), ["My", "Project2"]);
+		assert_eq!(words("v2Api"), ["v2", "Api"]);
+		assert_eq!(words("__"), Vec::<String>::new());
+	}
+
+	#[test]
+	fn apply_filters() {
+		let name = "my-coolProject";
+		assert_eq!(Filter::Snake.apply(name), "my_cool_project");
+		assert_eq!(Filter::Kebab.apply(name), "my-cool-project");
+		assert_eq!(Filter::Pascal.apply(name), "MyCoolProject");
+		assert_eq!(Filter::Camel.apply(name), "myCoolProject");
+		assert_eq!(Filter::Upper.apply(name), "MY-COOLPROJECT");
+		assert_eq!(Filter::Lower.apply(name), "my-coolproject");
+		assert_eq!(Filter::Title.apply(name), "My Cool Project");
+		assert_eq!(Filter::Pascal.apply("HTTP_server"), "HttpServer");
+
+		assert!("snake".parse::<Filter>().is_ok());
+		assert!("reverse".parse::<Filter>().is_err());
+	}
+}
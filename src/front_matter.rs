@@ -0,0 +1,149 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::path::Component;
+use std::path::Path;
+
+/// Settings for a single template, written as TOML between two `+++` lines at
+/// the very start of the file, which are removed from the output.
+///
+/// ```toml
+/// +++
+/// description = "A script to set up a development environment"
+/// required = ["name"]
+/// path = "scripts/setup.sh"
+/// mode = "755"
+/// +++
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+	/// Shown next to the template by `-list`
+	pub description: Option<String>,
+	/// Variables which must be set for the template to be created
+	pub required: Vec<String>,
+	/// Where to create the file, if it should be somewhere other than the path
+	/// of the template. Can use `$$variables$$`, just like template paths, but
+	/// must stay inside of the current directory.
+	#[serde(deserialize_with = "relative")]
+	pub path: Option<String>,
+	/// Permissions for the created file, in octal, like `chmod` takes them.
+	/// Only the permission bits can be set, and not setuid, setgid, or sticky.
+	#[serde(deserialize_with = "octal")]
+	pub mode: Option<u32>,
+	pub overwrite: Overwrite,
+}
+
+/// What to do if the file already exists
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overwrite {
+	#[default]
+	Always,
+	/// Leave the existing file as it is, which is useful for files that people
+	/// are expected to edit, like a README.
+	Never,
+}
+
+fn octal<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let mode = String::deserialize(deserializer)?;
+	u32::from_str_radix(&mode, 8)
+		.ok()
+		.filter(|mode| *mode <= 0o777)
+		.map(Some)
+		.ok_or_else(|| {
+			serde::de::Error::custom(format!("invalid mode \"{}\"", mode))
+		})
+}
+
+/// Whether a path is relative, and doesn't use `..` to leave the directory that
+/// it's relative to. Templates can come from anywhere, so they shouldn't be able
+/// to read or create files anywhere.
+pub fn stays_inside(path: &str) -> bool {
+	Path::new(path).components().all(|component| {
+		matches!(component, Component::Normal(_) | Component::CurDir)
+	})
+}
+
+fn relative<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let path = String::deserialize(deserializer)?;
+	if !stays_inside(&path) {
+		return Err(serde::de::Error::custom(format!(
+			"invalid path \"{}\", must be relative and can't use ..",
+			path
+		)));
+	}
+	Ok(Some(path))
+}
+
+/// Splits a template into its front matter, and the rest of the template. If
+/// the template doesn't start with `+++`, the front matter is just the default.
+pub fn split(text: &str) -> anyhow::Result<(FrontMatter, &str)> {
+	let Some(rest) =
+		text.strip_prefix("+++\n").or_else(|| text.strip_prefix("+++\r\n"))
+	else {
+		return Ok((FrontMatter::default(), text));
+	};
+
+	let mut offset = 0;
+	for line in rest.split_inclusive('\n') {
+		if line.trim_end() == "+++" {
+			let front_matter = toml::from_str(&rest[..offset])
+				.map_err(|err| anyhow!("invalid front matter: {}", err))?;
+			return Ok((front_matter, &rest[offset + line.len()..]));
+		}
+		offset += line.len();
+	}
+
+	Err(anyhow!("front matter is never closed with +++"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn split_front_matter() {
+		let text = "\
++++
+description = \"A setup script\"
+required = [\"name\"]
+mode = \"755\"
+overwrite = \"never\"
++++
+#!/bin/sh
+";
+		let (front_matter, body) = split(text).unwrap();
+		assert_eq!(
+			front_matter,
+			FrontMatter {
+				description: Some("A setup script".to_string()),
+				required: vec!["name".to_string()],
+				path: None,
+				mode: Some(0o755),
+				overwrite: Overwrite::Never,
+			}
+		);
+		assert_eq!(body, "#!/bin/sh\n");
+
+		let text = "# {{ name }}\n+++\n";
+		assert_eq!(split(text).unwrap(), (FrontMatter::default(), text));
+		assert_eq!(split("+++\r\n+++\r\nbody").unwrap().1, "body");
+
+		assert!(split("+++\ndescription = \"\"\n").is_err());
+		assert!(split("+++\nmode = \"789\"\n+++\n").is_err());
+		assert!(split("+++\nmode = \"4777\"\n+++\n").is_err());
+		assert!(split("+++\npath = \"../../outside.txt\"\n+++\n").is_err());
+		assert!(split("+++\npath = \"/etc/profile\"\n+++\n").is_err());
+		let (front_matter, _) =
+			split("+++\npath = \"./scripts/$$name$$.sh\"\n+++\n").unwrap();
+		assert_eq!(front_matter.path.as_deref(), Some("./scripts/$$name$$.sh"));
+		assert!(split("+++\ncolor = \"red\"\n+++\n").is_err());
+	}
+}
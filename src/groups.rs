@@ -14,9 +14,10 @@ pub fn expand_group<S: AsRef<str>>(
 		"/gleam" => Ok(with_common_files(&[
 			"Dockerfile@gleam",
 			"gleam.toml",
-			"src/$$name$$.gleam",
-			"test/$$name$$_test.gleam",
-			"test/$$name$$/example_test.gleam",
+			// Gleam module names have to be in snake case
+			"src/$$name|snake$$.gleam",
+			"test/$$name|snake$$_test.gleam",
+			"test/$$name|snake$$/example_test.gleam",
 		])),
 		"/go" => Ok(with_common_files(&["go.mod", "main.go", "staticcheck.conf"])),
 		"/rs" | "/rust" => Ok(with_common_files(&[
@@ -55,28 +56,25 @@ pub fn expand_group<S: AsRef<str>>(
 }
 
 #[cfg(test)]
-#[test]
-fn group_files_exist() {
-	use crate::parse_file_name;
-	use std::fs;
+#[tokio::test]
+async fn group_files_exist() {
+	use crate::Context;
+	use crate::fetch_file;
+	use crate::source::LocalSource;
 
+	let source = LocalSource::new("templates");
+	let ctx = Context::from([("name".to_string(), "cool-thing".into())]);
 	let groups = ["/oss", "/gleam", "/go", "/rs", "/ts", "/tsx"];
 	for group in groups {
-		let group = expand_group(group).unwrap();
-		let files = group.iter().map(|it| {
-			(
-				it,
-				parse_file_name(it)
-					.unwrap()
-					.1
-					.path()
-					.strip_prefix("/aslilac/bai/trunk/")
-					.unwrap()
-					.to_owned(),
-			)
-		});
-		for (name, path) in files {
-			assert!(fs::exists(&path).unwrap(), "missing file {name} at {path}");
+		for name in expand_group(group).unwrap() {
+			let file = fetch_file(&source, name).await;
+			assert!(file.is_ok(), "missing file {name}");
+
+			// The package name in gleam.toml is snake case, so the modules must be too
+			if group == "/gleam" && name.contains("$$") {
+				let path = file.unwrap().render(&ctx).path;
+				assert!(path.contains("cool_thing"), "{name} was created as {path}");
+			}
 		}
 	}
 }
@@ -1,68 +1,236 @@
+use anyhow::anyhow;
 use chrono::Datelike;
 use colored::Colorize;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::process::exit;
 use std::sync::Arc;
 use tokio::task;
 
 mod config;
+mod derive;
+mod expression;
+mod filters;
+mod front_matter;
 mod groups;
+mod manifest;
 mod options;
+mod prompt;
 mod regext;
+mod remote;
+mod source;
+mod template;
+mod toolchains;
+mod value;
 use config::Config;
+use config::SourceConfig;
+use front_matter::FrontMatter;
+use front_matter::Overwrite;
 use options::Options;
-
-static BASE: Lazy<reqwest::Url> = Lazy::new(|| {
-	reqwest::Url::parse(
-		"https://raw.githubusercontent.com/aslilac/bai/trunk/static/",
-	)
-	.expect("invalid base URL")
-});
+use remote::Remote;
+use source::TemplateSource;
+use value::Value;
 
 static IDENT: Lazy<&str> =
 	Lazy::new(|| include_str!("./ident.pcre").trim_end());
-static TEMPLATE_VARIABLE: Lazy<Regex> =
-	Lazy::new(|| Regex::new(&format!("\\{{\\{{ *{} *\\}}\\}}", *IDENT)).unwrap());
-static PATH_TEMPLATE_VARIABLE: Lazy<Regex> =
-	Lazy::new(|| Regex::new(&format!("\\$\\${}\\$\\$", *IDENT)).unwrap());
+static PATH_TEMPLATE_VARIABLE: Lazy<Regex> = Lazy::new(|| {
+	Regex::new(&format!("\\$\\${}(?<filters>(\\|[a-z]+)*)\\$\\$", *IDENT))
+		.unwrap()
+});
 
-type Context = HashMap<String, String>;
+type Context = HashMap<String, Value>;
 
-fn parse_file_name(file: &str) -> anyhow::Result<(&str, reqwest::Url)> {
-	let (file_path, tag) = file
+fn parse_file_name(file: &str) -> (&str, Option<String>) {
+	file
 		.rsplit_once("@")
 		.map(|(file_path, tag)| (file_path, Some(tag.to_ascii_lowercase())))
-		.unwrap_or((file, None));
+		.unwrap_or((file, None))
+}
 
-	let mut base = BASE.clone();
-	if let Some(tag) = tag {
-		base = base.join(&format!("@{tag}/"))?;
-	}
+/// A template which has been fetched, along with every partial that it
+/// includes, so that it's ready to be rendered.
+struct Fetched {
+	file: String,
+	front_matter: FrontMatter,
+	text: String,
+	partials: HashMap<String, String>,
+}
+
+/// A rendered template, and where it should be written to
+struct Output {
+	path: String,
+	text: String,
+	unresolved: Vec<String>,
+}
 
-	Ok((file_path, base.join(file_path)?))
+/// Filters in a path only change the name of the file that's created, so the
+/// template itself is stored without them, as `src/$$name$$.gleam` for
+/// `src/$$name|snake$$.gleam`. This also keeps `|` out of file names, which
+/// Windows doesn't allow.
+fn template_path(file_path: &str) -> Cow<'_, str> {
+	PATH_TEMPLATE_VARIABLE.replace_all(file_path, "$$$$${1}$$$$")
 }
 
-async fn fetch_file<C>(file: &str, ctx: C) -> anyhow::Result<()>
-where
-	C: AsRef<Context>,
-{
-	let ctx = ctx.as_ref();
-	let (file_path, url) = parse_file_name(file)?;
+async fn fetch_file(
+	source: &dyn TemplateSource,
+	file: &str,
+) -> anyhow::Result<Fetched> {
+	let (file_path, tag) = parse_file_name(file);
 
 	// Fetch file
-	let file_content =
-		reqwest::get(url).await?.error_for_status()?.text().await?;
+	let file_content = source
+		.fetch(&template_path(file_path), tag.as_deref())
+		.await?
+		.ok_or_else(|| anyhow!("template not found: {}", file))?;
+	let (front_matter, file_content) = front_matter::split(&file_content)
+		.map_err(|err| anyhow!("{}: {}", file, err))?;
+
+	let template = template::parse(file_content)
+		.map_err(|err| anyhow!("{}: {}", file, err))?;
+	let partials = template::fetch_partials(source, &template)
+		.await
+		.map_err(|err| anyhow!("{}: {}", file, err))?;
+	template::parse_partials(&partials)
+		.map_err(|err| anyhow!("{}: {}", file, err))?;
+
+	Ok(Fetched {
+		file: file.to_string(),
+		front_matter,
+		text: file_content.to_string(),
+		partials,
+	})
+}
 
-	let each = |captures: &regex::Captures| ctx.get(&captures[1]);
-	// Fill in template variables
-	let file_content = regext::for_each(&TEMPLATE_VARIABLE, file_content, each);
-	let file_path =
-		regext::for_each(&PATH_TEMPLATE_VARIABLE, file_path.to_string(), each);
+impl Fetched {
+	fn render(&self, ctx: &Context) -> Output {
+		// Both of these were already parsed successfully by `fetch_file`
+		let template = template::parse(&self.text).unwrap();
+		let partials = template::parse_partials(&self.partials).unwrap();
+
+		// Fill in template variables
+		let rendered = template.render(ctx, &partials);
+		let each = |captures: &regex::Captures| {
+			let value = ctx.get(&captures[1])?;
+			// Unknown filters leave the placeholder as it is, like unset variables do
+			let filters = template::parse_filters(&captures["filters"]).ok()?;
+			let value = filters
+				.iter()
+				.fold(value.clone(), |value, filter| filter.apply_value(&value));
+			Some(value.to_string())
+		};
+		let (file_path, _) = parse_file_name(&self.file);
+		let file_path = self.front_matter.path.as_deref().unwrap_or(file_path);
+		let path =
+			regext::for_each(&PATH_TEMPLATE_VARIABLE, file_path.to_string(), each);
+
+		let mut unresolved =
+			rendered.unresolved.into_iter().map(String::from).collect::<Vec<_>>();
+		for captures in PATH_TEMPLATE_VARIABLE.captures_iter(&path) {
+			let name = &captures[1];
+			if !unresolved.iter().any(|unresolved| unresolved == name) {
+				unresolved.push(name.to_string());
+			}
+		}
+
+		Output { path, text: rendered.text, unresolved }
+	}
+
+	/// Variables which the template needs, but which aren't set
+	fn missing(&self, ctx: &Context) -> Vec<String> {
+		let mut missing = self
+			.front_matter
+			.required
+			.iter()
+			.filter(|name| !ctx.contains_key(*name))
+			.cloned()
+			.collect::<Vec<_>>();
+		for name in self.render(ctx).unresolved {
+			if !missing.contains(&name) {
+				missing.push(name);
+			}
+		}
+		missing
+	}
+
+	/// Variables which have some text as a default, in the template or in any of
+	/// its partials
+	fn defaults(&self) -> Vec<(String, String)> {
+		std::iter::once(&self.text)
+			.chain(self.partials.values())
+			.flat_map(|text| template::parse(text).unwrap().defaults())
+			.map(|(name, text)| (name.to_string(), text.to_string()))
+			.collect()
+	}
+}
+
+fn write_file(
+	fetched: &Fetched,
+	ctx: &Context,
+	strict: bool,
+) -> anyhow::Result<()> {
+	let file = &fetched.file;
+	let front_matter = &fetched.front_matter;
+
+	let missing = front_matter
+		.required
+		.iter()
+		.filter(|name| !ctx.contains_key(*name))
+		.map(String::as_str)
+		.collect::<Vec<_>>();
+	if !missing.is_empty() {
+		return Err(anyhow!(
+			"{} requires variables which are not set: {}",
+			file,
+			missing.join(", ")
+		));
+	}
+
+	let Output { path: file_path, text, unresolved } = fetched.render(ctx);
+	// Variables can have anything in them, so check the path again now that
+	// they've been filled in
+	if !front_matter::stays_inside(&file_path) {
+		return Err(anyhow!(
+			"{} would be created at {}, which is outside of the current directory",
+			file,
+			file_path
+		));
+	}
+	if !unresolved.is_empty() {
+		if strict {
+			return Err(anyhow!(
+				"{} uses variables which are not set: {}",
+				file,
+				unresolved.join(", ")
+			));
+		}
+		for name in unresolved {
+			eprintln!(
+				"{} {} is not set, and was left as it is in {}",
+				"warning:".yellow(),
+				name,
+				file
+			);
+		}
+	}
+
+	if front_matter.overwrite == Overwrite::Never
+		&& Path::new(&file_path).exists()
+	{
+		eprintln!(
+			"{} {} already exists, and {} is set to never overwrite it",
+			"warning:".yellow(),
+			file_path,
+			file
+		);
+		return Ok(());
+	}
 
 	// Create parent directories as necessary
 	if let Some(parent) = Path::new(&file_path).parent()
@@ -70,28 +238,194 @@ where
 	{
 		fs::create_dir_all(parent)?;
 	}
-	fs::write(file_path, file_content)?;
+	fs::write(&file_path, text)?;
+
+	#[cfg(unix)]
+	if let Some(mode) = front_matter.mode {
+		use std::os::unix::fs::PermissionsExt;
+		fs::set_permissions(&file_path, fs::Permissions::from_mode(mode))?;
+	}
 
 	Ok(())
 }
 
+/// Asks for any variables which the templates need, but which aren't set.
+/// Suggestions come from the defaults that the templates give them elsewhere.
+fn prompt_missing(
+	fetched: &[Fetched],
+	ctx: &mut Context,
+	rules: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+	let mut missing = Vec::new();
+	for name in fetched.iter().flat_map(|fetched| fetched.missing(ctx)) {
+		if !missing.contains(&name) {
+			missing.push(name);
+		}
+	}
+	if missing.is_empty() {
+		return Ok(());
+	}
+
+	let defaults = fetched.iter().flat_map(Fetched::defaults).collect::<Vec<_>>();
+	let missing = missing
+		.iter()
+		.map(|name| {
+			let suggestion = defaults
+				.iter()
+				.find(|(default, _)| default == name)
+				.map(|(_, text)| text.as_str());
+			(name.as_str(), suggestion)
+		})
+		.collect::<Vec<_>>();
+
+	let answers = prompt::variables(&missing)?;
+	if answers.is_empty() {
+		return Ok(());
+	}
+	if prompt::confirm("save these answers to your config file?")? {
+		Config::set_context(answers.iter().map(|(name, value)| (name, value)))?;
+	}
+	ctx.extend(answers.into_iter().map(|(name, value)| (name, value.into())));
+	// Other variables might be derived from the answers
+	derive::derive(ctx, rules)
+}
+
+fn apply_aliases(context: &mut Context, aliases: Vec<(String, String)>) {
+	for (alias, canonical_name) in aliases {
+		if context.contains_key(&canonical_name) {
+			if !context.contains_key(&alias) {
+				context.insert(alias, context[&canonical_name].clone());
+			} else {
+				eprintln!(
+					"{0} {1} was aliased to {2}, but {1} is already set",
+					"warning:".yellow(),
+					alias,
+					canonical_name,
+				);
+			}
+		} else {
+			eprintln!(
+				"{0} {1} was aliased to {2}, but {2} is not set",
+				"warning:".yellow(),
+				alias,
+				canonical_name,
+			);
+		}
+	}
+}
+
+/// Whether the current directory is the top of a Git clone, rather than
+/// somewhere inside of one
+fn is_git_top_level() -> bool {
+	let Ok(output) =
+		Command::new("git").args(["rev-parse", "--show-toplevel"]).output()
+	else {
+		return false;
+	};
+	if !output.status.success() {
+		return false;
+	}
+	let top_level = String::from_utf8_lossy(&output.stdout);
+	match (
+		fs::canonicalize(top_level.trim()),
+		env::current_dir().and_then(fs::canonicalize),
+	) {
+		(Ok(top_level), Ok(current_dir)) => top_level == current_dir,
+		_ => false,
+	}
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-	let Options { files, mut context, aliases } =
+	let Options { files, mut context, aliases, sources, git_ref, list, strict } =
 		Options::try_from(&*env::args().skip(1).collect::<Vec<_>>())?;
 
 	let mut config = Config::load()?;
-	// Copy context variables defined as arguments _over_ context variables loaded
-	// from the config file.
+	let strict = strict || config.strict;
+	// Sources passed as arguments take precedence over the config file.
+	let sources = if !sources.is_empty() {
+		sources.into_iter().map(SourceConfig::from).collect()
+	} else if !config.source.is_empty() {
+		std::mem::take(&mut config.source)
+	} else {
+		vec![SourceConfig::default()]
+	};
+	let source: Arc<dyn TemplateSource> =
+		source::parse_all(&sources, git_ref.as_deref())?.into();
+
+	if list {
+		let names = source.list().await?;
+		let width = names.iter().map(String::len).max().unwrap_or_default();
+		for name in names {
+			let (file_path, tag) = parse_file_name(&name);
+			// Descriptions are just a nicety, so if we can't read the template, we
+			// can still list it without one.
+			let description = match source.fetch(file_path, tag.as_deref()).await {
+				Ok(Some(text)) => front_matter::split(&text)
+					.ok()
+					.and_then(|(front_matter, _)| front_matter.description),
+				_ => None,
+			};
+			match description {
+				Some(description) => {
+					println!("{:width$}  {}", name, description.dimmed())
+				}
+				None => println!("{}", name),
+			}
+		}
+		return Ok(());
+	}
+
+	// Variables from the config file are overridden by those from the
+	// environment, which are overridden by those defined as arguments.
+	let env = env::vars_os().filter_map(|(key, value)| {
+		Some((key.into_string().ok()?, value.into_string().ok()?))
+	});
+	config.context.extend(options::context_from_env(env));
 	config.context.extend(context);
 	context = config.context;
 
+	// When adding files to an existing project, its manifest knows best
+	if let Ok(dir) = env::current_dir() {
+		for (key, value) in manifest::read(&dir) {
+			context.entry(key).or_insert(value);
+		}
+	}
+
+	// Inside of an existing clone, the remote tells us who owns the repository,
+	// and what it's called. A subdirectory might be a separate project though,
+	// so the repository only names the project at the top of the clone.
+	let at_top_level = is_git_top_level();
+	let output =
+		Command::new("git").args(["remote", "get-url", "origin"]).output();
+	if let Ok(output) = output
+		&& output.status.success()
+		&& let Some(remote) =
+			Remote::parse(String::from_utf8_lossy(&output.stdout).trim())
+	{
+		if at_top_level && remote.host == "github.com" {
+			context
+				.entry("github.owner".to_string())
+				.or_insert_with(|| remote.owner.clone().into());
+		}
+		for (key, value) in [
+			("repo.host", remote.host.clone()),
+			("repo.owner", remote.owner.clone()),
+			("repo.url", remote.url()),
+			("repo.name", remote.name),
+		] {
+			context.entry(key.to_string()).or_insert_with(|| value.into());
+		}
+	}
+
 	if !context.contains_key("name") {
-		if let Some(dir) = env::current_dir()
+		if let Some(repo_name) = context.get("repo.name").filter(|_| at_top_level) {
+			context.insert("name".to_string(), repo_name.clone());
+		} else if let Some(dir) = env::current_dir()
 			.ok()
 			.and_then(|dir| dir.file_name().map(|name| name.to_os_string()))
 		{
-			context.insert("name".to_string(), dir.to_string_lossy().to_string());
+			context.insert("name".to_string(), dir.to_string_lossy().as_ref().into());
 		} else {
 			eprintln!(
 				"{} name is unset, but is used by many templates",
@@ -111,11 +445,11 @@ async fn main() -> anyhow::Result<()> {
 			&& output.status.success()
 		{
 			let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-			context.insert("git.branch".to_string(), stdout);
+			context.insert("git.branch".to_string(), stdout.into());
 		} else {
 			// The command might fail if a value hasn't been set, but we should just
 			// gracefully fall back to Git's default.
-			context.insert("git.branch".to_string(), "master".to_string());
+			context.insert("git.branch".to_string(), "master".into());
 		}
 	};
 
@@ -127,7 +461,7 @@ async fn main() -> anyhow::Result<()> {
 		{
 			// Ouch. Two allocations in one line.
 			let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-			context.insert("author.name".to_string(), stdout);
+			context.insert("author.name".to_string(), stdout.into());
 		} else {
 			eprintln!(
 				"{} author.name is unset, but is used by many templates",
@@ -152,7 +486,7 @@ async fn main() -> anyhow::Result<()> {
 		{
 			// Ouch. Two allocations in one line.
 			let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-			context.insert("author.email".to_string(), stdout);
+			context.insert("author.email".to_string(), stdout.into());
 		} else {
 			eprintln!(
 				"{} author.email is unset, but is used by many templates",
@@ -170,92 +504,66 @@ async fn main() -> anyhow::Result<()> {
 	};
 
 	if !context.contains_key("date.year") {
-		context
-			.insert("date.year".to_string(), chrono::Local::now().year().to_string());
-	}
-
-	if context.contains_key("github.username")
-		&& !context.contains_key("github.owner")
-	{
 		context.insert(
-			"github.owner".to_string(),
-			context["github.username"].to_string(),
+			"date.year".to_string(),
+			chrono::Local::now().year().to_string().into(),
 		);
 	}
 
-	if context.contains_key("github.owner")
-		&& !context.contains_key("author.homepage")
-	{
-		context.insert(
-			"author.homepage".to_string(),
-			format!("{}.github.io", context["github.owner"]),
-		);
+	// Aliases of variables which are already set are applied first, so that
+	// they can be used to derive other variables. The rest might be aliases of
+	// derived variables, so they have to wait until after.
+	let (aliases, derived_aliases): (Vec<_>, Vec<_>) = aliases
+		.into_iter()
+		.partition(|(_, canonical_name)| context.contains_key(canonical_name));
+	apply_aliases(&mut context, aliases);
+	derive::derive(&mut context, &config.derive)?;
+	apply_aliases(&mut context, derived_aliases);
+
+	let mut tasks = task::JoinSet::new();
+	for (i, file) in files.into_iter().enumerate() {
+		let source = source.clone();
+		tasks.spawn(async move { (i, fetch_file(&*source, &file).await) });
 	}
 
-	for (alias, canonical_name) in aliases {
-		if context.contains_key(&canonical_name) {
-			if !context.contains_key(&alias) {
-				context.insert(alias, context[&canonical_name].clone());
-			} else {
-				eprintln!(
-					"{0} {1} was aliased to {2}, but {1} is already set",
-					"warning:".yellow(),
-					alias,
-					canonical_name,
-				);
+	let mut failed = false;
+	let mut fetched = Vec::new();
+	while let Some(result) = tasks.join_next().await {
+		match result? {
+			(i, Ok(file)) => fetched.push((i, file)),
+			(_, Err(err)) => {
+				eprintln!("{} {}", "error:".red(), err);
+				failed = true;
 			}
-		} else {
-			eprintln!(
-				"{0} {1} was aliased to {2}, but {2} is not set",
-				"warning:".yellow(),
-				alias,
-				canonical_name,
-			);
 		}
 	}
-
-	if context.contains_key("author.name")
-		&& !context.contains_key("licence.owner")
-		&& !context.contains_key("license.owner")
-	{
-		context
-			.insert("licence.owner".to_string(), context["author.name"].to_string());
-		context
-			.insert("license.owner".to_string(), context["author.name"].to_string());
-	}
-
-	if context.contains_key("license.owner")
-		&& !context.contains_key("licence.owner")
-	{
-		context.insert(
-			"licence.owner".to_string(),
-			context["license.owner"].to_string(),
-		);
+	// Keep the files in the order they were given, so that questions are too
+	fetched.sort_by_key(|(i, _)| *i);
+	let fetched = fetched.into_iter().map(|(_, file)| file).collect::<Vec<_>>();
+
+	// Toolchains are only asked for their versions if a template needs them
+	let missing = fetched
+		.iter()
+		.flat_map(|fetched| fetched.missing(&context))
+		.collect::<Vec<_>>();
+	if toolchains::detect(&mut context, &missing) {
+		derive::derive(&mut context, &config.derive)?;
 	}
 
-	if context.contains_key("licence.owner")
-		&& !context.contains_key("license.owner")
-	{
-		context.insert(
-			"license.owner".to_string(),
-			context["licence.owner"].to_string(),
-		);
+	if prompt::is_interactive() {
+		prompt_missing(&fetched, &mut context, &config.derive)?;
 	}
 
-	let context = Arc::new(context);
-	let mut tasks = task::JoinSet::new();
-	for file in files {
-		let context = context.clone();
-		tasks.spawn(async move {
-			if let Err(err) = fetch_file(&file, context).await {
-				eprintln!("{} {}", "error:".red(), err);
-			};
-		});
+	for file in &fetched {
+		if let Err(err) = write_file(file, &context, strict) {
+			eprintln!("{} {}", "error:".red(), err);
+			failed = true;
+		}
 	}
 
-	while !tasks.is_empty() {
-		// `tasks` is not empty, and must return a result
-		tasks.join_next().await.unwrap()?;
+	// In strict mode, any file we couldn't create is a failure
+	if strict && failed {
+		exit(1);
 	}
 
 	Ok(())
@@ -267,55 +575,64 @@ mod tests {
 
 	#[test]
 	fn parse_tagged_file_name() {
-		let (file_path, url) = parse_file_name("Cargo.toml").unwrap();
-		assert_eq!(file_path, "Cargo.toml");
-		assert_eq!(url, BASE.join("Cargo.toml").unwrap());
-
-		let (file_path, url) = parse_file_name("Cargo.toml@rust").unwrap();
-		assert_eq!(file_path, "Cargo.toml");
-		assert_eq!(url, BASE.join("@rust/Cargo.toml").unwrap());
+		assert_eq!(parse_file_name("Cargo.toml"), ("Cargo.toml", None));
+		assert_eq!(
+			parse_file_name("Cargo.toml@Rust"),
+			("Cargo.toml", Some("rust".to_string())),
+		);
 	}
 
 	#[test]
 	fn parse_template_replacements() {
-		let file_content = "Hi, my name is {{ name }}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "name");
-		assert_eq!(captures.get(0).unwrap().range(), 15..25);
+		let variables = |text| template::parse(text).unwrap().variables().0;
+		assert_eq!(variables("Hi, my name is {{ name }}!"), ["name"]);
 
 		// No spaces is fine
-		let file_content = "Hi, my name is {{name}}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "name");
-		assert_eq!(captures.get(0).unwrap().range(), 15..23);
+		assert_eq!(variables("Hi, my name is {{name}}!"), ["name"]);
 
 		// Multiple spaces is fine
-		let file_content = "Hi, my name is {{  name  }}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "name");
-		assert_eq!(captures.get(0).unwrap().range(), 15..27);
+		assert_eq!(variables("Hi, my name is {{  name  }}!"), ["name"]);
 
 		// `.` and `:` are fine
-		let file_content = "Hi, my name is {{ github.username }}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "github.username");
-		assert_eq!(captures.get(0).unwrap().range(), 15..36);
+		assert_eq!(
+			variables("Hi, my name is {{ github.username }}!"),
+			["github.username"]
+		);
 
 		// Numbers are fine, except at the start
-		let file_content = "Hi, my name is {{ 0a.1b }}!";
-		assert!(TEMPLATE_VARIABLE.captures(file_content).is_none());
-		let file_content = "Hi, my name is {{ a0.1b }}!";
-		assert!(TEMPLATE_VARIABLE.captures(file_content).is_none());
-		let file_content = "Hi, my name is {{ a0.b1 }}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "a0.b1");
-		assert_eq!(captures.get(0).unwrap().range(), 15..26);
-		let file_content = "Hi, my name is {{ a0.1 }}!";
-		let captures = TEMPLATE_VARIABLE.captures(file_content).unwrap();
-		assert_eq!(&captures[1], "a0.1");
-		assert_eq!(captures.get(0).unwrap().range(), 15..25);
-		let file_content = "Hi, my name is {{ 0.1 }}!";
-		assert!(TEMPLATE_VARIABLE.captures(file_content).is_none());
+		assert!(variables("Hi, my name is {{ 0a.1b }}!").is_empty());
+		assert!(variables("Hi, my name is {{ a0.1b }}!").is_empty());
+		assert_eq!(variables("Hi, my name is {{ a0.b1 }}!"), ["a0.b1"]);
+		assert_eq!(variables("Hi, my name is {{ a0.1 }}!"), ["a0.1"]);
+		assert!(variables("Hi, my name is {{ 0.1 }}!").is_empty());
+
+		// Filters apply to the variable
+		assert_eq!(
+			variables("Hi, my name is {{ name | pascal | upper }}!"),
+			["name"]
+		);
+
+		let path = "src/$$name|snake$$.gleam";
+		let captures = PATH_TEMPLATE_VARIABLE.captures(path).unwrap();
+		assert_eq!(&captures[1], "name");
+		assert_eq!(&captures["filters"], "|snake");
+		assert_eq!(template_path(path), "src/$$name$$.gleam");
+		assert_eq!(
+			template_path("$$a|upper$$/$$b$$/$$c|snake|kebab$$"),
+			"$$a$$/$$b$$/$$c$$"
+		);
+
+		// So do defaults, and only the last resort needs to be set
+		let template =
+			template::parse(r#"Hi, my name is {{ name ?? nickname ?? "James" }}!"#)
+				.unwrap();
+		assert_eq!(template.variables(), (vec![], vec!["name", "nickname"]));
+
+		// As well as simple expressions
+		assert_eq!(
+			variables(r#"{{ github.owner + "/" + name }}"#),
+			["github.owner", "name"]
+		);
 
 		// New lines, tabs, etc., are not fine
 		let file_content = r#"
@@ -323,6 +640,7 @@ mod tests {
 				name
 			}}!
 		"#;
-		assert!(TEMPLATE_VARIABLE.captures(file_content).is_none());
+		assert!(variables(file_content).is_empty());
+		assert!(variables("Hi, my name is {{\tname }}!").is_empty());
 	}
 }
@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Context;
+
+/// Details about a project, read from the manifest of whatever language it's
+/// written in, for when bai is adding files to a project that already exists.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Manifest {
+	name: Option<String>,
+	/// Written like `McKayla Washburn <mckayla@hey.com>`, although the email is
+	/// optional
+	author: Option<String>,
+	license: Option<String>,
+	description: Option<String>,
+}
+
+fn parse_cargo_toml(text: &str) -> Option<Manifest> {
+	let manifest = text.parse::<toml::Table>().ok()?;
+	let package = manifest.get("package")?.as_table()?;
+	// Fields inherited from a workspace are tables, and are skipped
+	let string = |key| package.get(key)?.as_str().map(String::from);
+	Some(Manifest {
+		name: string("name"),
+		author: package
+			.get("authors")
+			.and_then(|authors| authors.as_array()?.first()?.as_str())
+			.map(String::from),
+		license: string("license"),
+		description: string("description"),
+	})
+}
+
+fn parse_package_json(text: &str) -> Option<Manifest> {
+	let manifest = serde_json::from_str::<serde_json::Value>(text).ok()?;
+	let string = |key| manifest.get(key)?.as_str().map(String::from);
+	// The author can be written as a string, or as an object
+	let author = manifest.get("author").and_then(|author| match author {
+		serde_json::Value::Object(author) => {
+			let name = author.get("name").and_then(|name| name.as_str());
+			let email = author.get("email").and_then(|email| email.as_str());
+			match (name, email) {
+				(Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+				(Some(name), None) => Some(name.to_string()),
+				_ => None,
+			}
+		}
+		author => author.as_str().map(String::from),
+	});
+	Some(Manifest {
+		name: string("name"),
+		author,
+		license: string("license"),
+		description: string("description"),
+	})
+}
+
+fn parse_gleam_toml(text: &str) -> Option<Manifest> {
+	let manifest = text.parse::<toml::Table>().ok()?;
+	let string = |key| manifest.get(key)?.as_str().map(String::from);
+	Some(Manifest {
+		name: string("name"),
+		author: None,
+		license: manifest
+			.get("licences")
+			.and_then(|licences| licences.as_array()?.first()?.as_str())
+			.map(String::from),
+		description: string("description"),
+	})
+}
+
+/// Only the name can be found in a `go.mod`, as the last part of the module
+/// path, ignoring any major version suffix like `/v2`.
+fn parse_go_mod(text: &str) -> Option<Manifest> {
+	let module = text
+		.lines()
+		.find_map(|line| line.trim().strip_prefix("module "))?
+		.trim()
+		.trim_matches('"');
+	let mut parts = module.rsplit('/');
+	let mut name = parts.next()?;
+	let is_major_version = name
+		.strip_prefix('v')
+		.is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+	if is_major_version {
+		name = parts.next()?;
+	}
+	Some(Manifest { name: Some(name.to_string()), ..Default::default() })
+}
+
+type Parser = fn(&str) -> Option<Manifest>;
+
+static MANIFESTS: &[(&str, Parser)] = &[
+	("Cargo.toml", parse_cargo_toml),
+	("package.json", parse_package_json),
+	("gleam.toml", parse_gleam_toml),
+	("go.mod", parse_go_mod),
+];
+
+impl Manifest {
+	fn into_context(self) -> Context {
+		let mut ctx = Context::new();
+		let mut set = |key: &str, value: Option<String>| {
+			if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+				ctx.insert(key.to_string(), value.trim().into());
+			}
+		};
+
+		set("name", self.name);
+		set("license", self.license);
+		set("description", self.description);
+		if let Some(author) = self.author {
+			match author.split_once('<') {
+				Some((name, rest)) => {
+					set("author.name", Some(name.to_string()));
+					set(
+						"author.email",
+						rest.split_once('>').map(|(email, _)| email.to_string()),
+					);
+				}
+				None => set("author.name", Some(author)),
+			}
+		}
+		ctx
+	}
+}
+
+/// Reads variables from any manifests in `dir`. If there's more than one, the
+/// first one in `MANIFESTS` to set a variable wins.
+pub fn read(dir: &Path) -> Context {
+	let mut ctx = Context::new();
+	for (file_name, parse) in MANIFESTS {
+		let Ok(text) = fs::read_to_string(dir.join(file_name)) else {
+			continue;
+		};
+		let Some(manifest) = parse(&text) else {
+			continue;
+		};
+		for (key, value) in manifest.into_context() {
+			ctx.entry(key).or_insert(value);
+		}
+	}
+	ctx
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::value::Value;
+
+	#[test]
+	fn cargo_toml() {
+		let ctx = parse_cargo_toml(
+			r#"
+[package]
+name = "bai"
+authors = ["McKayla Washburn <mckayla@hey.com>"]
+description = ""
+license.workspace = true
+"#,
+		)
+		.unwrap()
+		.into_context();
+		assert_eq!(ctx.len(), 3);
+		assert_eq!(ctx["name"], Value::from("bai"));
+		assert_eq!(ctx["author.name"], Value::from("McKayla Washburn"));
+		assert_eq!(ctx["author.email"], Value::from("mckayla@hey.com"));
+
+		assert_eq!(parse_cargo_toml("[workspace]\nmembers = []\n"), None);
+	}
+
+	#[test]
+	fn package_json() {
+		let ctx = parse_package_json(
+			r#"{
+	"name": "@aslilac/bai",
+	"author": { "name": "McKayla", "email": "mckayla@hey.com" },
+	"license": "MPL-2.0",
+	"description": "Templates"
+}"#,
+		)
+		.unwrap()
+		.into_context();
+		assert_eq!(ctx["name"], Value::from("@aslilac/bai"));
+		assert_eq!(ctx["author.name"], Value::from("McKayla"));
+		assert_eq!(ctx["author.email"], Value::from("mckayla@hey.com"));
+		assert_eq!(ctx["license"], Value::from("MPL-2.0"));
+		assert_eq!(ctx["description"], Value::from("Templates"));
+
+		let manifest =
+			parse_package_json(r#"{ "author": "McKayla (https://mckay.la)" }"#)
+				.unwrap();
+		assert_eq!(manifest.author.as_deref(), Some("McKayla (https://mckay.la)"));
+
+		let ctx = parse_package_json(r#"{ "name": "bai", "license": "ISC" }"#)
+			.unwrap()
+			.into_context();
+		assert_eq!(ctx["name"], Value::from("bai"));
+		assert_eq!(ctx["license"], Value::from("ISC"));
+		assert!(!ctx.contains_key("author.name"));
+	}
+
+	#[test]
+	fn gleam_toml() {
+		let ctx = parse_gleam_toml("name = \"bai\"\nlicences = [\"Apache-2.0\"]\n")
+			.unwrap()
+			.into_context();
+		assert_eq!(ctx["name"], Value::from("bai"));
+		assert_eq!(ctx["license"], Value::from("Apache-2.0"));
+	}
+
+	#[test]
+	fn go_mod() {
+		let name = |text| parse_go_mod(text).and_then(|manifest| manifest.name);
+		assert_eq!(
+			name("module github.com/aslilac/bai\n\ngo 1.22\n").as_deref(),
+			Some("bai")
+		);
+		assert_eq!(
+			name("module github.com/aslilac/bai/v2\n").as_deref(),
+			Some("bai")
+		);
+		assert_eq!(name("module bai\n").as_deref(), Some("bai"));
+		assert_eq!(name("go 1.22\n"), None);
+	}
+}
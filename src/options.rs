@@ -3,9 +3,9 @@ use colored::Colorize;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::BTreeSet;
-use std::collections::HashMap;
 use std::process::exit;
 
+use crate::Context;
 use crate::IDENT;
 use crate::config::Config;
 use crate::groups::expand_group;
@@ -16,8 +16,13 @@ static VARIABLE_NAME: Lazy<Regex> =
 #[derive(Clone, Debug)]
 pub struct Options {
 	pub files: BTreeSet<String>,
-	pub context: HashMap<String, String>,
+	pub context: Context,
 	pub aliases: Vec<(String, String)>,
+	pub sources: Vec<String>,
+	pub git_ref: Option<String>,
+	pub list: bool,
+	/// Fail, rather than warn, when a template uses a variable that isn't set
+	pub strict: bool,
 }
 
 impl<S, const N: usize> TryFrom<&[S; N]> for Options
@@ -31,6 +36,39 @@ where
 	}
 }
 
+/// Prefix of environment variables which set context variables
+const ENV_PREFIX: &str = "BAI_CTX_";
+
+/// Reads context variables from the environment, where `BAI_CTX_author_email`
+/// sets `author.email`. Since environment variables can't contain a `.`, a
+/// single `_` stands for one, and `__` stands for an actual `_`.
+pub fn context_from_env<I>(vars: I) -> Context
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	vars
+		.into_iter()
+		.filter_map(|(key, value)| {
+			let name = key
+				.strip_prefix(ENV_PREFIX)?
+				.split("__")
+				.map(|part| part.replace('_', "."))
+				.collect::<Vec<_>>()
+				.join("_");
+			if !VARIABLE_NAME.is_match(&name) {
+				eprintln!(
+					"{} ignoring {}, because \"{}\" is not a valid variable name",
+					"warning:".yellow(),
+					key,
+					name
+				);
+				return None;
+			}
+			Some((name, value.into()))
+		})
+		.collect()
+}
+
 fn help() {
 	println!("{}", include_str!("./help.txt"));
 	exit(0);
@@ -90,8 +128,12 @@ where
 
 		let mut args = args.iter();
 		let mut files = Vec::new();
-		let mut context = HashMap::new();
+		let mut context = Context::new();
 		let mut aliases = vec![];
+		let mut sources = vec![];
+		let mut git_ref = None;
+		let mut list = false;
+		let mut strict = false;
 
 		while let Some(arg) = args.next() {
 			let arg = arg.as_ref();
@@ -111,7 +153,13 @@ where
 					VARIABLE_NAME
 						.find_at(key, 0)
 						.ok_or_else(|| anyhow!("key \"{}\" is invalid", key))?;
-					context.insert(key.to_string(), value.to_string());
+					// Defining the same variable more than once makes it a list
+					match context.get_mut(key) {
+						Some(existing) => existing.push(value.to_string()),
+						None => {
+							context.insert(key.to_string(), value.into());
+						}
+					}
 				}
 				"-use" | "--use" | "-alias" | "--alias" | "-A" => {
 					let alias = args
@@ -133,6 +181,26 @@ where
 					})?;
 					aliases.push((alias.to_string(), canonical_name.to_string()));
 				}
+				"-source" | "--source" => {
+					let url = args
+						.next()
+						.ok_or_else(|| anyhow!("expected a source after {}", arg))?
+						.as_ref();
+					sources.push(url.to_string());
+				}
+				"-ref" | "--ref" => {
+					let value = args
+						.next()
+						.ok_or_else(|| anyhow!("expected a ref after {}", arg))?
+						.as_ref();
+					git_ref = Some(value.to_string());
+				}
+				"-list" | "--list" => {
+					list = true;
+				}
+				"-strict" | "--strict" => {
+					strict = true;
+				}
 				_ => {
 					if arg.len() >= 2 && arg.starts_with('-') {
 						return Err(anyhow!("unrecognized option: {}", arg));
@@ -159,13 +227,14 @@ where
 			})
 			.collect();
 
-		Ok(Options { files, context, aliases })
+		Ok(Options { files, context, aliases, sources, git_ref, list, strict })
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::value::Value;
 
 	#[test]
 	fn groups() {
@@ -173,6 +242,68 @@ mod tests {
 		assert!(options.files.contains("gleam.toml"));
 	}
 
+	#[test]
+	fn source() {
+		let options = Options::try_from(&["LICENSE"]).unwrap();
+		assert!(options.sources.is_empty());
+
+		let options = Options::try_from(&[
+			"--source",
+			"./overrides/",
+			"--source",
+			"https://example.com/",
+			"LICENSE",
+		])
+		.unwrap();
+		assert_eq!(options.sources, ["./overrides/", "https://example.com/"]);
+		assert!(options.files.contains("LICENSE"));
+
+		assert!(Options::try_from(&["LICENSE", "--source"]).is_err());
+
+		let options = Options::try_from(&["--ref", "v1.0.3", "LICENSE"]).unwrap();
+		assert_eq!(options.git_ref.as_deref(), Some("v1.0.3"));
+	}
+
+	#[test]
+	fn define() {
+		let options =
+			Options::try_from(&["-d", "name=bai", "-d", "keywords=cli", "LICENSE"])
+				.unwrap();
+		assert_eq!(options.context["name"], Value::from("bai"));
+		assert_eq!(options.context["keywords"], Value::from("cli"));
+
+		let options = Options::try_from(&[
+			"-d",
+			"keywords=cli",
+			"-d",
+			"keywords=templates",
+			"LICENSE",
+		])
+		.unwrap();
+		assert_eq!(
+			options.context["keywords"],
+			Value::from(vec!["cli".to_string(), "templates".to_string()]),
+		);
+	}
+
+	#[test]
+	fn env() {
+		let vars = [
+			("BAI_CTX_author_email", "mckayla@hey.com"),
+			("BAI_CTX_license__owner", "McKayla"),
+			("BAI_CTX_name", "bai"),
+			("BAI_CTX_bad_", "?"),
+			("HOME", "/home/mckayla"),
+		];
+		let context = context_from_env(
+			vars.iter().map(|(key, value)| (key.to_string(), value.to_string())),
+		);
+		assert_eq!(context.len(), 3);
+		assert_eq!(context["author.email"], Value::from("mckayla@hey.com"));
+		assert_eq!(context["license_owner"], Value::from("McKayla"));
+		assert_eq!(context["name"], Value::from("bai"));
+	}
+
 	#[test]
 	fn parse_identifier() {
 		assert!(VARIABLE_NAME.find_at("a", 0).is_some());
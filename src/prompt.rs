@@ -0,0 +1,47 @@
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+
+/// Whether we can ask questions, which is only when someone is there to
+/// answer them.
+pub fn is_interactive() -> bool {
+	io::stdin().is_terminal()
+}
+
+/// Asks a question, and returns the answer without any surrounding whitespace.
+pub fn ask(question: &str) -> io::Result<String> {
+	let mut stdout = io::stdout();
+	write!(stdout, "{} ", question)?;
+	stdout.flush()?;
+
+	let mut answer = String::new();
+	io::stdin().read_line(&mut answer)?;
+	Ok(answer.trim().to_string())
+}
+
+/// Asks for the value of each variable, suggesting a default for those which
+/// have one. Variables which are left blank, without a default, are skipped.
+pub fn variables(
+	variables: &[(&str, Option<&str>)],
+) -> io::Result<Vec<(String, String)>> {
+	let mut answers = Vec::new();
+	for (name, suggestion) in variables {
+		let answer = match suggestion {
+			Some(suggestion) => {
+				let answer = ask(&format!("{} [{}]?", name, suggestion))?;
+				if answer.is_empty() { suggestion.to_string() } else { answer }
+			}
+			None => ask(&format!("{}?", name))?,
+		};
+		if !answer.is_empty() {
+			answers.push((name.to_string(), answer));
+		}
+	}
+	Ok(answers)
+}
+
+/// Asks a yes or no question, where no is the default.
+pub fn confirm(question: &str) -> io::Result<bool> {
+	let answer = ask(&format!("{} [y/N]", question))?;
+	Ok(matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes"))
+}
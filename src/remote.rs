@@ -0,0 +1,107 @@
+/// A repository on a forge like GitHub, GitLab, or Gitea, as described by the
+/// URL of a Git remote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Remote {
+	pub host: String,
+	/// The user or organization which owns the repository. For GitLab, this
+	/// can include subgroups, like `group/subgroup`.
+	pub owner: String,
+	pub name: String,
+}
+
+impl Remote {
+	/// Parses a remote URL, in either SSH or HTTPS form:
+	///
+	/// - `git@github.com:aslilac/bai.git`
+	/// - `ssh://git@gitea.example.com:2222/aslilac/bai.git`
+	/// - `https://gitlab.com/aslilac/bai`
+	pub fn parse(url: &str) -> Option<Self> {
+		let (host, path) = match url.split_once("://") {
+			Some((scheme, rest)) => {
+				if !matches!(scheme, "https" | "http" | "ssh" | "git") {
+					return None;
+				}
+				let (authority, path) = rest.split_once('/')?;
+				let host =
+					authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+				let host = host.split_once(':').map_or(host, |(host, _)| host);
+				(host, path)
+			}
+			// scp-like syntax, which is always SSH
+			None => {
+				let (authority, path) = url.split_once(':')?;
+				let host =
+					authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+				(host, path)
+			}
+		};
+
+		let path = path.trim_matches('/');
+		let path = path.strip_suffix(".git").unwrap_or(path);
+		let (owner, name) = path.rsplit_once('/')?;
+		if host.is_empty() || owner.is_empty() || name.is_empty() {
+			return None;
+		}
+
+		Some(Remote {
+			host: host.to_string(),
+			owner: owner.to_string(),
+			name: name.to_string(),
+		})
+	}
+
+	/// The URL of the repository's web page
+	pub fn url(&self) -> String {
+		format!("https://{}/{}/{}", self.host, self.owner, self.name)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_remotes() {
+		let bai = Remote {
+			host: "github.com".to_string(),
+			owner: "aslilac".to_string(),
+			name: "bai".to_string(),
+		};
+		assert_eq!(
+			Remote::parse("git@github.com:aslilac/bai.git"),
+			Some(bai.clone())
+		);
+		assert_eq!(Remote::parse("git@github.com:aslilac/bai"), Some(bai.clone()));
+		assert_eq!(
+			Remote::parse("https://github.com/aslilac/bai.git"),
+			Some(bai.clone())
+		);
+		assert_eq!(
+			Remote::parse("https://github.com/aslilac/bai/"),
+			Some(bai.clone())
+		);
+		assert_eq!(
+			Remote::parse("https://aslilac@github.com/aslilac/bai"),
+			Some(bai.clone())
+		);
+		assert_eq!(
+			Remote::parse("ssh://git@github.com/aslilac/bai.git"),
+			Some(bai)
+		);
+
+		let remote =
+			Remote::parse("ssh://git@gitea.example.com:2222/aslilac/bai.git")
+				.unwrap();
+		assert_eq!(remote.host, "gitea.example.com");
+		assert_eq!(remote.url(), "https://gitea.example.com/aslilac/bai");
+
+		let remote =
+			Remote::parse("git@gitlab.com:group/subgroup/bai.git").unwrap();
+		assert_eq!(remote.owner, "group/subgroup");
+		assert_eq!(remote.url(), "https://gitlab.com/group/subgroup/bai");
+
+		assert_eq!(Remote::parse("/home/aslilac/code/bai"), None);
+		assert_eq!(Remote::parse("file:///home/aslilac/code/bai"), None);
+		assert_eq!(Remote::parse("https://github.com/bai"), None);
+	}
+}
@@ -0,0 +1,389 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::header;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::config::SourceConfig;
+
+mod archive;
+mod cache;
+#[cfg(any(test, feature = "embed"))]
+mod embedded;
+mod git;
+mod http;
+mod layered;
+mod local;
+pub use archive::ArchiveSource;
+use cache::HttpCache;
+#[cfg(feature = "embed")]
+pub use embedded::BUNDLED;
+#[cfg(test)]
+pub use embedded::EmbeddedSource;
+pub use git::GitSource;
+pub use http::HttpSource;
+pub use layered::LayeredSource;
+pub use local::LocalSource;
+
+/// A collection of templates, which might be on the network, on disk, or
+/// compiled into the binary.
+#[async_trait]
+pub trait TemplateSource: Send + Sync {
+	/// Fetches the content of the template at `path`, using the variant for `tag`
+	/// if given. Returns `None` if the source doesn't have the template.
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>>;
+
+	/// Lists the name of every template available from the source, in the same
+	/// `file@tag` form that they can be requested with.
+	async fn list(&self) -> anyhow::Result<Vec<String>>;
+}
+
+/// Where a source reads its templates from, for sources which can read them
+/// from either a URL or a local path.
+pub enum Location {
+	Path(PathBuf),
+	Url(reqwest::Url),
+}
+
+/// Templates which use the newer syntax, like blocks and partials, are kept in
+/// `templates/`. `static/` is left as it was for older versions of bai, which
+/// would write that syntax out as it is.
+pub static DEFAULT_SOURCE: &str =
+	"https://raw.githubusercontent.com/aslilac/bai/trunk/templates/";
+
+/// Where templates were kept before `templates/`, which doesn't exist at refs
+/// from before it was added. When the default source is pinned to a ref, any
+/// template that isn't in `templates/` is looked for here instead.
+pub static LEGACY_SOURCE: &str =
+	"https://raw.githubusercontent.com/aslilac/bai/trunk/static/";
+
+/// Parses a source from the config file or command line. `ref_override` pins
+/// the source to a ref, taking precedence over the one in its config, but is
+/// ignored by sources which don't have refs (like local directories, or HTTP
+/// sources other than GitHub).
+pub fn parse(
+	source: &SourceConfig,
+	ref_override: Option<&str>,
+) -> anyhow::Result<Box<dyn TemplateSource>> {
+	let url = source.url.as_deref().unwrap_or(DEFAULT_SOURCE);
+	let git_ref = ref_override.or(source.git_ref.as_deref());
+	let headers = headers(source)?;
+
+	if let Some(remote) = url.strip_prefix("git+") {
+		if !headers.is_empty() {
+			return Err(anyhow!(
+				"invalid source \"{}\": git sources use git's own credentials, and can't have headers or a token",
+				url
+			));
+		}
+		let checkouts =
+			Config::cache_dir()?.join("git").join(git::dir_name(remote));
+		let mut git = GitSource::new(remote, checkouts);
+		if let Some(git_ref) = git_ref {
+			git = git.with_ref(git_ref);
+		}
+		if let Some(subdirectory) = &source.path {
+			git = git.with_subdirectory(subdirectory);
+		}
+		return Ok(Box::new(git));
+	}
+
+	let location = match reqwest::Url::parse(url) {
+		Ok(parsed) if parsed.scheme() == "file" => Location::Path(
+			parsed
+				.to_file_path()
+				.map_err(|_| anyhow!("invalid source \"{}\": not a valid path", url))?,
+		),
+		// A single letter scheme is almost certainly a Windows drive letter
+		Ok(parsed) if parsed.scheme().len() > 1 => Location::Url(parsed),
+		// Anything that isn't a URL is treated as a local path
+		_ => Location::Path(std::path::absolute(match url.strip_prefix("~/") {
+			Some(path) => etcetera::home_dir()?.join(path),
+			None => PathBuf::from(url),
+		})?),
+	};
+
+	if archive::is_archive(url) {
+		if source.git_ref.is_some() {
+			return Err(anyhow!(
+				"invalid source \"{}\": archives can't be pinned to a ref",
+				url
+			));
+		}
+		if matches!(location, Location::Path(_)) && !headers.is_empty() {
+			return Err(anyhow!(
+				"invalid source \"{}\": only HTTP sources can have headers or a token",
+				url
+			));
+		}
+		let mut archive = ArchiveSource::new(location)?.with_headers(headers)?;
+		if let Some(subdirectory) = &source.path {
+			archive = archive.with_subdirectory(subdirectory);
+		}
+		return Ok(Box::new(archive));
+	}
+
+	if source.path.is_some() {
+		return Err(anyhow!(
+			"invalid source \"{}\": only git and archive sources can have a path",
+			url
+		));
+	}
+
+	let path = match location {
+		Location::Url(url) => {
+			// Only a ref from the source's own config has to be usable
+			let git_ref = match source.git_ref {
+				Some(_) => git_ref,
+				None => git_ref.filter(|_| HttpSource::can_be_pinned(&url)),
+			};
+			let http = http_source(url, headers.clone(), git_ref)?;
+			if source.url.is_none()
+				&& let Some(git_ref) = git_ref
+			{
+				let legacy = reqwest::Url::parse(LEGACY_SOURCE)?;
+				let legacy = http_source(legacy, headers, Some(git_ref))?;
+				return Ok(Box::new(LayeredSource::new(vec![http, legacy])));
+			}
+			return Ok(http);
+		}
+		Location::Path(path) => path,
+	};
+
+	if source.git_ref.is_some() {
+		return Err(anyhow!(
+			"invalid source \"{}\": local directories can't be pinned to a ref",
+			url
+		));
+	}
+	if !headers.is_empty() {
+		return Err(anyhow!(
+			"invalid source \"{}\": only HTTP sources can have headers or a token",
+			url
+		));
+	}
+	Ok(Box::new(LocalSource::new(path)))
+}
+
+/// Collects the headers to send to an HTTP source, including an `Authorization`
+/// header if the source has a token.
+fn headers(source: &SourceConfig) -> anyhow::Result<HeaderMap> {
+	let mut headers = HeaderMap::new();
+	for (name, value) in &source.headers {
+		headers.insert(
+			HeaderName::try_from(name)
+				.map_err(|_| anyhow!("invalid header name \"{}\"", name))?,
+			HeaderValue::try_from(value)
+				.map_err(|_| anyhow!("invalid value for header \"{}\"", name))?,
+		);
+	}
+
+	let token = match (&source.token, &source.token_env) {
+		(Some(_), Some(_)) => {
+			return Err(anyhow!("a source can't have both `token` and `token_env`"));
+		}
+		(Some(token), None) => Some(token.clone()),
+		(None, Some(var)) => Some(env::var(var).map_err(|_| {
+			anyhow!("environment variable {} (from `token_env`) is not set", var)
+		})?),
+		(None, None) => None,
+	};
+	if let Some(token) = token {
+		let mut value =
+			HeaderValue::try_from(format!("Bearer {}", token.trim()))
+				.map_err(|_| anyhow!("token contains invalid characters"))?;
+		value.set_sensitive(true);
+		headers.insert(header::AUTHORIZATION, value);
+	}
+
+	Ok(headers)
+}
+
+/// Parses a list of sources, in order of priority, into a single source. When
+/// built with the `embed` feature, the bundled templates are used as a last
+/// resort, for when none of the other sources are reachable.
+fn http_source(
+	url: reqwest::Url,
+	headers: HeaderMap,
+	git_ref: Option<&str>,
+) -> anyhow::Result<Box<dyn TemplateSource>> {
+	let mut http = HttpSource::new(url).with_headers(headers)?;
+	if let Some(git_ref) = git_ref {
+		http = http.with_ref(git_ref)?;
+	}
+	// If we can't figure out where to put the cache, we can still go without
+	match Config::cache_dir() {
+		Ok(dir) => Ok(Box::new(http.with_cache(HttpCache::new(dir.join("http"))))),
+		Err(_) => Ok(Box::new(http)),
+	}
+}
+
+pub fn parse_all(
+	sources: &[SourceConfig],
+	ref_override: Option<&str>,
+) -> anyhow::Result<Box<dyn TemplateSource>> {
+	let sources = sources.iter().map(|source| parse(source, ref_override));
+	#[cfg(feature = "embed")]
+	let sources =
+		sources.chain([Ok(Box::new(BUNDLED) as Box<dyn TemplateSource>)]);
+	let mut sources = sources.collect::<anyhow::Result<Vec<_>>>()?;
+
+	if sources.len() == 1 {
+		return Ok(sources.pop().unwrap());
+	}
+	Ok(Box::new(LayeredSource::new(sources)))
+}
+
+/// Returns the path of a template relative to the root of a source, which is
+/// where templates are laid out the same way in every kind of source.
+fn relative_path(path: &str, tag: Option<&str>) -> String {
+	match tag {
+		Some(tag) => format!("@{tag}/{path}"),
+		None => path.to_string(),
+	}
+}
+
+/// The inverse of `relative_path`, for listing templates. Partials are only
+/// meant to be included by other templates, so they aren't listed.
+fn template_name(relative_path: &Path) -> Option<String> {
+	let mut components =
+		relative_path.iter().map(|component| component.to_string_lossy());
+	let first = components.next().unwrap_or_default();
+	let rest = components.collect::<Vec<_>>().join("/");
+
+	let name = match first.strip_prefix('@') {
+		Some(tag) if !rest.is_empty() => format!("{rest}@{tag}"),
+		_ if rest.is_empty() => first.to_string(),
+		_ => format!("{first}/{rest}"),
+	};
+	(!name.starts_with("_partials/")).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn source(url: &str) -> SourceConfig {
+		SourceConfig::from(url.to_string())
+	}
+
+	#[test]
+	fn parse_sources() {
+		assert!(parse(&source("https://example.com/templates/"), None).is_ok());
+		assert!(parse(&source("file:///home/me/bai/static"), None).is_ok());
+		assert!(parse(&source("templates"), None).is_ok());
+		assert!(parse(&SourceConfig::default(), None).is_ok());
+		assert!(
+			parse_all(&[source("./overrides/"), source("templates")], None).is_ok()
+		);
+	}
+
+	#[test]
+	fn parse_pinned_sources() {
+		let pinned = SourceConfig {
+			git_ref: Some("v1.0.3".to_string()),
+			..source("templates")
+		};
+		assert!(parse(&pinned, None).is_err());
+		// Local directories don't have refs, so overriding them is fine
+		assert!(parse(&source("templates"), Some("v1.0.3")).is_ok());
+		assert!(parse(&SourceConfig::default(), Some("v1.0.3")).is_ok());
+		// ...and so is overriding HTTP sources which aren't on GitHub, as long as
+		// they aren't pinned in the config
+		assert!(parse(&source("https://example.com/"), Some("v1.0.3")).is_ok());
+		let pinned = SourceConfig {
+			git_ref: Some("v1.0.3".to_string()),
+			..source("https://example.com/")
+		};
+		assert!(parse(&pinned, None).is_err());
+		assert!(parse(&pinned, Some("v1.0.4")).is_err());
+		assert!(
+			parse_all(
+				&[source("https://example.com/"), SourceConfig::default()],
+				Some("v1.0.3")
+			)
+			.is_ok()
+		);
+	}
+
+	#[test]
+	fn parse_authenticated_sources() {
+		let http = SourceConfig {
+			headers: [("X-Team".to_string(), "templates".to_string())].into(),
+			token: Some("abc123".to_string()),
+			..source("https://example.com/")
+		};
+		let headers = headers(&http).unwrap();
+		assert_eq!(headers["x-team"], "templates");
+		assert_eq!(headers[header::AUTHORIZATION], "Bearer abc123");
+		assert!(parse(&http, None).is_ok());
+
+		let local =
+			SourceConfig { token: Some("abc123".to_string()), ..source("templates") };
+		assert!(parse(&local, None).is_err());
+
+		let missing_env = SourceConfig {
+			token_env: Some("BAI_TEST_TOKEN_THAT_IS_NOT_SET".to_string()),
+			..source("https://example.com/")
+		};
+		assert!(parse(&missing_env, None).is_err());
+	}
+
+	#[test]
+	fn parse_archive_sources() {
+		let archive = SourceConfig {
+			path: Some("bai-trunk/static".to_string()),
+			..source("https://example.com/bai-trunk.tar.gz")
+		};
+		assert!(parse(&archive, None).is_ok());
+		assert!(parse(&source("./templates.zip"), Some("v1.0.3")).is_ok());
+
+		let pinned = SourceConfig {
+			git_ref: Some("v1.0.3".to_string()),
+			..source("./templates.zip")
+		};
+		assert!(parse(&pinned, None).is_err());
+	}
+
+	#[test]
+	fn parse_git_sources() {
+		let git = SourceConfig {
+			path: Some("static".to_string()),
+			..source("git+https://github.com/aslilac/bai.git")
+		};
+		assert!(parse(&git, Some("v1.0.3")).is_ok());
+		assert!(
+			parse(&source("git+ssh://git@github.com/aslilac/bai.git"), None).is_ok()
+		);
+
+		let not_git =
+			SourceConfig { path: Some("static".to_string()), ..source("templates") };
+		assert!(parse(&not_git, None).is_err());
+	}
+
+	#[test]
+	fn template_names() {
+		assert_eq!(relative_path("LICENSE", None), "LICENSE");
+		assert_eq!(relative_path("LICENSE", Some("mpl")), "@mpl/LICENSE");
+
+		let name = |path| template_name(Path::new(path));
+		assert_eq!(name("LICENSE").as_deref(), Some("LICENSE"));
+		assert_eq!(name("@mpl/LICENSE").as_deref(), Some("LICENSE@mpl"));
+		assert_eq!(
+			name("@rust/.github/workflows/check.yml").as_deref(),
+			Some(".github/workflows/check.yml@rust"),
+		);
+		assert_eq!(name("src/main.rs").as_deref(), Some("src/main.rs"));
+		assert_eq!(name("_partials/github/checkout.yml"), None);
+		assert_eq!(name("@rust/_partials/github/setup.yml"), None);
+	}
+}
@@ -0,0 +1,284 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use reqwest::header::HeaderMap;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Cursor;
+use std::io::Read;
+use std::path::Path;
+use tokio::sync::OnceCell;
+use tokio::task;
+
+use super::Location;
+use super::TemplateSource;
+use super::http::check_status;
+use super::relative_path;
+use super::template_name;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArchiveKind {
+	Tar,
+	TarGz,
+	Zip,
+}
+
+impl ArchiveKind {
+	fn from_name(name: &str) -> Option<Self> {
+		// Ignore any query string or fragment if the name is a URL
+		let name = name.split(['?', '#']).next().unwrap_or_default();
+		let name = name.to_ascii_lowercase();
+		if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+			Some(ArchiveKind::TarGz)
+		} else if name.ends_with(".tar") {
+			Some(ArchiveKind::Tar)
+		} else if name.ends_with(".zip") {
+			Some(ArchiveKind::Zip)
+		} else {
+			None
+		}
+	}
+}
+
+/// Returns true if `name` looks like an archive that `ArchiveSource` can read.
+pub fn is_archive(name: &str) -> bool {
+	ArchiveKind::from_name(name).is_some()
+}
+
+/// Templates from a `.tar.gz`, `.tar`, or `.zip` file, either on disk or
+/// downloaded from a URL. The whole archive is read into memory the first time a
+/// template is needed.
+pub struct ArchiveSource {
+	location: Location,
+	kind: ArchiveKind,
+	client: reqwest::Client,
+	authenticated: bool,
+	subdirectory: Option<String>,
+	files: OnceCell<BTreeMap<String, String>>,
+}
+
+impl ArchiveSource {
+	pub fn new(location: Location) -> anyhow::Result<Self> {
+		let name = match &location {
+			Location::Path(path) => path.to_string_lossy().to_string(),
+			Location::Url(url) => url.path().to_string(),
+		};
+		let kind = ArchiveKind::from_name(&name)
+			.ok_or_else(|| anyhow!("{} is not a supported kind of archive", name))?;
+
+		Ok(ArchiveSource {
+			location,
+			kind,
+			client: reqwest::Client::new(),
+			authenticated: false,
+			subdirectory: None,
+			files: OnceCell::new(),
+		})
+	}
+
+	/// Sends `headers` with the request to download the archive, usually to
+	/// authenticate.
+	pub fn with_headers(mut self, headers: HeaderMap) -> anyhow::Result<Self> {
+		self.authenticated = !headers.is_empty();
+		self.client =
+			reqwest::Client::builder().default_headers(headers).build()?;
+		Ok(self)
+	}
+
+	/// Uses templates from a directory inside the archive, rather than from the
+	/// root of it.
+	pub fn with_subdirectory<S: Into<String>>(mut self, subdirectory: S) -> Self {
+		self.subdirectory = Some(subdirectory.into());
+		self
+	}
+
+	async fn files(&self) -> anyhow::Result<&BTreeMap<String, String>> {
+		self
+			.files
+			.get_or_try_init(|| async {
+				let bytes = match &self.location {
+					Location::Path(path) => fs::read(path).map_err(|err| {
+						anyhow!("failed to read {}: {}", path.display(), err)
+					})?,
+					Location::Url(url) => {
+						let response = self.client.get(url.clone()).send().await?;
+						check_status(response, self.authenticated)?.bytes().await?.to_vec()
+					}
+				};
+
+				let kind = self.kind;
+				let subdirectory = self.subdirectory.clone();
+				task::spawn_blocking(move || {
+					index(kind, &bytes, subdirectory.as_deref())
+				})
+				.await?
+			})
+			.await
+	}
+}
+
+/// Reads every file in the archive into memory, keyed by its path relative to
+/// `subdirectory`, or the root of the archive.
+fn index(
+	kind: ArchiveKind,
+	bytes: &[u8],
+	subdirectory: Option<&str>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+	let subdirectory = subdirectory.map(|it| it.trim_matches('/'));
+	let mut files = BTreeMap::new();
+	let mut insert = |path: &Path, reader: &mut dyn Read| -> anyhow::Result<()> {
+		let path = path.to_string_lossy().replace('\\', "/");
+		let path = path.trim_start_matches("./");
+		let path = match subdirectory {
+			Some(subdirectory) => match path
+				.strip_prefix(subdirectory)
+				.and_then(|path| path.strip_prefix('/'))
+			{
+				Some(path) => path,
+				None => return Ok(()),
+			},
+			None => path,
+		};
+
+		let mut content = String::new();
+		// Templates are always text, so anything else can be skipped
+		if reader.read_to_string(&mut content).is_ok() {
+			files.insert(path.to_string(), content);
+		}
+		Ok(())
+	};
+
+	match kind {
+		ArchiveKind::Tar | ArchiveKind::TarGz => {
+			let reader: Box<dyn Read> = match kind {
+				ArchiveKind::TarGz => Box::new(GzDecoder::new(bytes)),
+				_ => Box::new(bytes),
+			};
+			let mut archive = tar::Archive::new(reader);
+			for entry in archive.entries()? {
+				let mut entry = entry?;
+				if entry.header().entry_type().is_file() {
+					let path = entry.path()?.into_owned();
+					insert(&path, &mut entry)?;
+				}
+			}
+		}
+		ArchiveKind::Zip => {
+			let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+			for i in 0..archive.len() {
+				let mut file = archive.by_index(i)?;
+				if file.is_file()
+					&& let Some(path) = file.enclosed_name()
+				{
+					insert(&path, &mut file)?;
+				}
+			}
+		}
+	}
+
+	Ok(files)
+}
+
+#[async_trait]
+impl TemplateSource for ArchiveSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		Ok(self.files().await?.get(&relative_path(path, tag)).cloned())
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		Ok(
+			self
+				.files()
+				.await?
+				.keys()
+				.filter_map(|path| template_name(Path::new(path)))
+				.collect(),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use flate2::Compression;
+	use flate2::write::GzEncoder;
+	use std::env;
+	use std::io::Write;
+
+	static FILES: &[(&str, &str)] = &[
+		("bai-trunk/static/README.md", "# {{ name }}\n"),
+		("bai-trunk/static/@mpl/LICENSE", "MPL\n"),
+		("bai-trunk/README.md", "# bai\n"),
+	];
+
+	fn tar_gz() -> Vec<u8> {
+		let mut builder =
+			tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+		for (path, content) in FILES {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(content.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+			builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+		}
+		builder.into_inner().unwrap().finish().unwrap()
+	}
+
+	fn zip() -> Vec<u8> {
+		let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+		for (path, content) in FILES {
+			writer
+				.start_file(*path, zip::write::SimpleFileOptions::default())
+				.unwrap();
+			writer.write_all(content.as_bytes()).unwrap();
+		}
+		writer.finish().unwrap().into_inner()
+	}
+
+	#[test]
+	fn archive_kinds() {
+		assert!(is_archive("templates.tar.gz"));
+		assert!(is_archive("https://example.com/templates.TGZ?token=abc"));
+		assert!(is_archive("templates.zip"));
+		assert!(!is_archive("templates"));
+		assert!(!is_archive("templates.gz"));
+	}
+
+	#[test]
+	fn index_archives() {
+		for (kind, bytes) in
+			[(ArchiveKind::TarGz, tar_gz()), (ArchiveKind::Zip, zip())]
+		{
+			let files = index(kind, &bytes, Some("bai-trunk/static/")).unwrap();
+			assert_eq!(
+				files.keys().collect::<Vec<_>>(),
+				["@mpl/LICENSE", "README.md"],
+				"{kind:?}",
+			);
+			assert_eq!(files["README.md"], "# {{ name }}\n");
+
+			let files = index(kind, &bytes, None).unwrap();
+			assert_eq!(files.len(), 3, "{kind:?}");
+		}
+	}
+
+	#[tokio::test]
+	async fn fetch_from_local_archive() {
+		let path = env::temp_dir().join("bai-test-archive-source.tar.gz");
+		fs::write(&path, tar_gz()).unwrap();
+
+		let source = ArchiveSource::new(Location::Path(path.clone()))
+			.unwrap()
+			.with_subdirectory("bai-trunk/static");
+		let license = source.fetch("LICENSE", Some("mpl")).await.unwrap();
+		assert_eq!(license.as_deref(), Some("MPL\n"));
+		assert!(source.fetch("LICENSE", None).await.unwrap().is_none());
+		assert_eq!(source.list().await.unwrap(), ["LICENSE@mpl", "README.md"]);
+
+		_ = fs::remove_file(&path);
+	}
+}
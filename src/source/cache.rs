@@ -0,0 +1,108 @@
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A copy of a template as it was last fetched, along with the headers needed to
+/// check if it has changed since.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct CacheEntry {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub etag: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_modified: Option<String>,
+	pub body: String,
+}
+
+/// Stores fetched templates on disk, so that they don't need to be downloaded
+/// again if they haven't changed, and can still be used while offline.
+pub struct HttpCache {
+	root: PathBuf,
+}
+
+impl HttpCache {
+	pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+		HttpCache { root: root.into() }
+	}
+
+	fn entry_path(&self, url: &Url) -> PathBuf {
+		let mut path = self.root.join(match url.port() {
+			Some(port) => format!("{}_{}", url.host_str().unwrap_or_default(), port),
+			None => url.host_str().unwrap_or_default().to_string(),
+		});
+		path.extend(url.path_segments().into_iter().flatten());
+		path.as_mut_os_string().push(".toml");
+		path
+	}
+
+	pub fn load(&self, url: &Url) -> Option<CacheEntry> {
+		// A missing or corrupt entry just means we have to fetch the file again
+		let content = fs::read_to_string(self.entry_path(url)).ok()?;
+		toml::from_str(&content).ok()
+	}
+
+	pub fn store(&self, url: &Url, entry: &CacheEntry) -> anyhow::Result<()> {
+		let path = self.entry_path(url);
+		if let Some(parent) = path.parent()
+			&& !parent.exists()
+		{
+			fs::create_dir_all(parent)?;
+		}
+		fs::write(path, toml::to_string(entry)?)?;
+		Ok(())
+	}
+
+	pub fn remove(&self, url: &Url) -> io::Result<()> {
+		match fs::remove_file(self.entry_path(url)) {
+			Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::env;
+
+	#[test]
+	fn entry_paths() {
+		let cache = HttpCache::new("/cache");
+		let url = Url::parse("https://example.com/templates/@mpl/LICENSE").unwrap();
+		assert_eq!(
+			cache.entry_path(&url),
+			PathBuf::from("/cache/example.com/templates/@mpl/LICENSE.toml"),
+		);
+		let url = Url::parse("http://localhost:8080/Cargo.toml").unwrap();
+		assert_eq!(
+			cache.entry_path(&url),
+			PathBuf::from("/cache/localhost_8080/Cargo.toml.toml"),
+		);
+	}
+
+	#[test]
+	fn store_and_load_entries() {
+		let root = env::temp_dir().join("bai-test-http-cache");
+		_ = fs::remove_dir_all(&root);
+		let cache = HttpCache::new(&root);
+
+		let url = Url::parse("https://example.com/templates/README.md").unwrap();
+		assert_eq!(cache.load(&url), None);
+
+		let entry = CacheEntry {
+			etag: Some("\"abc123\"".to_string()),
+			last_modified: None,
+			body: "# {{ name }}\n".to_string(),
+		};
+		cache.store(&url, &entry).unwrap();
+		assert_eq!(cache.load(&url), Some(entry));
+
+		cache.remove(&url).unwrap();
+		assert_eq!(cache.load(&url), None);
+		cache.remove(&url).unwrap();
+
+		_ = fs::remove_dir_all(&root);
+	}
+}
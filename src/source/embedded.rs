@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use std::path::Path;
+
+use super::TemplateSource;
+use super::relative_path;
+use super::template_name;
+
+/// Templates which are compiled into the binary, keyed by their path relative
+/// to the root of the source.
+#[derive(Clone, Copy)]
+pub struct EmbeddedSource {
+	files: &'static [(&'static str, &'static str)],
+}
+
+impl EmbeddedSource {
+	pub const fn new(files: &'static [(&'static str, &'static str)]) -> Self {
+		EmbeddedSource { files }
+	}
+}
+
+/// The entire `templates/` directory, bundled into the binary at build time.
+#[cfg(feature = "embed")]
+pub static BUNDLED: EmbeddedSource =
+	EmbeddedSource::new(include!(concat!(env!("OUT_DIR"), "/templates.rs")));
+
+#[async_trait]
+impl TemplateSource for EmbeddedSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		let path = relative_path(path, tag);
+		Ok(
+			self
+				.files
+				.iter()
+				.find(|(file_path, _)| *file_path == path)
+				.map(|(_, content)| content.to_string()),
+		)
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		let mut files = self
+			.files
+			.iter()
+			.filter_map(|(file_path, _)| template_name(Path::new(file_path)))
+			.collect::<Vec<_>>();
+		files.sort();
+		Ok(files)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static SOURCE: EmbeddedSource = EmbeddedSource::new(&[
+		("README.md", "# {{ name }}\n"),
+		("@mpl/LICENSE", "Mozilla Public License\n"),
+	]);
+
+	#[tokio::test]
+	async fn fetch_embedded_templates() {
+		let readme = SOURCE.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# {{ name }}\n"));
+		assert!(SOURCE.fetch("LICENSE", None).await.unwrap().is_none());
+		assert_eq!(SOURCE.list().await.unwrap(), ["LICENSE@mpl", "README.md"]);
+	}
+
+	#[cfg(feature = "embed")]
+	#[tokio::test]
+	async fn fetch_bundled_templates() {
+		let readme = BUNDLED.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# {{ name }}\n"));
+		let files = BUNDLED.list().await.unwrap();
+		assert!(files.contains(&".github/workflows/check.yml@rust".to_string()));
+	}
+}
@@ -0,0 +1,240 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use colored::Colorize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use tokio::sync::OnceCell;
+use tokio::task;
+
+use super::LocalSource;
+use super::TemplateSource;
+
+/// Templates from a git repository, which is shallowly fetched into a local
+/// checkout the first time a template is needed. Each ref has a checkout of its
+/// own, so that a copy fetched earlier is never mistaken for a different ref.
+pub struct GitSource {
+	remote: String,
+	git_ref: Option<String>,
+	/// The directory which holds a checkout for each ref
+	checkouts: PathBuf,
+	subdirectory: Option<String>,
+	local: OnceCell<LocalSource>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<()> {
+	let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+	if !output.status.success() {
+		return Err(anyhow!(
+			"git {} failed: {}",
+			args.first().unwrap_or(&""),
+			String::from_utf8_lossy(&output.stderr).trim()
+		));
+	}
+
+	Ok(())
+}
+
+/// Turns a remote or ref into the name of a directory. Anything other than
+/// letters, numbers, `-` and `_` is percent-encoded, so that two names which
+/// only differ in punctuation, like `feature/x` and `feature.x`, never share a
+/// directory.
+pub fn dir_name(name: &str) -> String {
+	let mut dir_name = String::with_capacity(name.len());
+	for byte in name.bytes() {
+		if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+			dir_name.push(byte as char);
+		} else {
+			dir_name.push_str(&format!("%{:02X}", byte));
+		}
+	}
+	dir_name
+}
+
+/// Brings `checkout` up to date with `git_ref` from `remote`, cloning it first if
+/// necessary.
+fn sync(remote: &str, git_ref: &str, checkout: &Path) -> anyhow::Result<()> {
+	if !checkout.join(".git").exists() {
+		std::fs::create_dir_all(checkout)?;
+		git(checkout, &["init", "-q"])?;
+		git(checkout, &["remote", "add", "origin", remote])?;
+	}
+
+	if let Err(err) =
+		git(checkout, &["fetch", "-q", "--depth", "1", "origin", git_ref])
+	{
+		// If we've fetched this ref before, we can keep using that copy. A failed
+		// fetch clears FETCH_HEAD, so the copy that's checked out is all we have.
+		if git(checkout, &["rev-parse", "-q", "--verify", "HEAD"]).is_err() {
+			return Err(err);
+		}
+		eprintln!(
+			"{} unable to fetch {}, using the copy of {} that was fetched last time",
+			"warning:".yellow(),
+			remote,
+			git_ref,
+		);
+		return Ok(());
+	}
+
+	git(
+		checkout,
+		&[
+			"-c",
+			"advice.detachedHead=false",
+			"checkout",
+			"-q",
+			"--force",
+			"FETCH_HEAD",
+		],
+	)
+}
+
+impl GitSource {
+	/// `remote` is anything that `git fetch` understands, and `checkouts` is the
+	/// directory that each ref will be fetched into a subdirectory of.
+	pub fn new<S, P>(remote: S, checkouts: P) -> Self
+	where
+		S: Into<String>,
+		P: Into<PathBuf>,
+	{
+		GitSource {
+			remote: remote.into(),
+			git_ref: None,
+			checkouts: checkouts.into(),
+			subdirectory: None,
+			local: OnceCell::new(),
+		}
+	}
+
+	pub fn with_ref<S: Into<String>>(mut self, git_ref: S) -> Self {
+		self.git_ref = Some(git_ref.into());
+		self
+	}
+
+	/// Uses templates from a directory inside the repository, rather than from
+	/// the root of it.
+	pub fn with_subdirectory<S: Into<String>>(mut self, subdirectory: S) -> Self {
+		self.subdirectory = Some(subdirectory.into());
+		self
+	}
+
+	async fn local(&self) -> anyhow::Result<&LocalSource> {
+		self
+			.local
+			.get_or_try_init(|| async {
+				let remote = self.remote.clone();
+				let git_ref =
+					self.git_ref.clone().unwrap_or_else(|| "HEAD".to_string());
+				let checkout = self.checkouts.join(dir_name(&git_ref));
+				let root = match &self.subdirectory {
+					Some(subdirectory) => checkout.join(subdirectory),
+					None => checkout.clone(),
+				};
+				task::spawn_blocking(move || sync(&remote, &git_ref, &checkout))
+					.await?
+					.map_err(|err| anyhow!("unable to fetch {}: {}", self.remote, err))?;
+
+				Ok(LocalSource::new(root))
+			})
+			.await
+	}
+}
+
+#[async_trait]
+impl TemplateSource for GitSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		self.local().await?.fetch(path, tag).await
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		let mut files = self.local().await?.list().await?;
+		// The checkout has a .git directory, which is not a template
+		files.retain(|file| !file.starts_with(".git/"));
+		Ok(files)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::env;
+	use std::fs;
+
+	fn git(dir: &Path, args: &[&str]) {
+		super::git(dir, args).unwrap();
+	}
+
+	#[test]
+	fn dir_names() {
+		assert_eq!(dir_name("v1.0.3"), "v1%2E0%2E3");
+		assert_eq!(dir_name("feature/x"), "feature%2Fx");
+		assert_eq!(dir_name("feature_x"), "feature_x");
+		assert_ne!(dir_name("feature/x"), dir_name("feature.x"));
+		assert_eq!(dir_name(".."), "%2E%2E");
+	}
+
+	#[tokio::test]
+	async fn fetch_from_bare_repository() {
+		let root = env::temp_dir().join("bai-test-git-source");
+		_ = fs::remove_dir_all(&root);
+		let work = root.join("work");
+		let bare = root.join("templates.git");
+
+		// Make a repository with a couple of versions of a template in it
+		fs::create_dir_all(work.join("static/@mpl")).unwrap();
+		git(&work, &["init", "-q"]);
+		let commit =
+			["-c", "user.name=James Baxter", "-c", "user.email=jb@hey.com"];
+		fs::write(work.join("static/README.md"), "# v1\n").unwrap();
+		fs::write(work.join("static/@mpl/LICENSE"), "MPL\n").unwrap();
+		git(&work, &["add", "."]);
+		git(&work, &[&commit[..], &["commit", "-q", "-m", "v1"]].concat());
+		git(&work, &["tag", "v1"]);
+		fs::write(work.join("static/README.md"), "# v2\n").unwrap();
+		git(&work, &[&commit[..], &["commit", "-q", "-a", "-m", "v2"]].concat());
+		git(&work, &["tag", "v2"]);
+		let work = work.to_string_lossy().to_string();
+		git(&root, &["clone", "-q", "--bare", &work, "templates.git"]);
+
+		let remote = format!("file://{}", bare.display());
+		let source = GitSource::new(&remote, root.join("checkout"))
+			.with_subdirectory("static");
+		let readme = source.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# v2\n"));
+		let license = source.fetch("LICENSE", Some("mpl")).await.unwrap();
+		assert_eq!(license.as_deref(), Some("MPL\n"));
+		assert_eq!(source.list().await.unwrap(), ["LICENSE@mpl", "README.md"]);
+
+		let source = GitSource::new(&remote, root.join("checkout"))
+			.with_ref("v1")
+			.with_subdirectory("static");
+		let readme = source.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# v1\n"));
+
+		// Without the remote, refs which were fetched before can still be used,
+		// but others can't, rather than using whatever was fetched last
+		fs::rename(&bare, root.join("moved.git")).unwrap();
+		let source = GitSource::new(&remote, root.join("checkout"))
+			.with_ref("v1")
+			.with_subdirectory("static");
+		let readme = source.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# v1\n"));
+		let source = GitSource::new(&remote, root.join("checkout"))
+			.with_ref("v2")
+			.with_subdirectory("static");
+		assert!(source.fetch("README.md", None).await.is_err());
+
+		let source = GitSource::new(
+			root.join("nope").display().to_string(),
+			root.join("nope-checkout"),
+		);
+		assert!(source.fetch("README.md", None).await.is_err());
+
+		_ = fs::remove_dir_all(&root);
+	}
+}
@@ -0,0 +1,243 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use colored::Colorize;
+use reqwest::StatusCode;
+use reqwest::Url;
+use reqwest::header;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use super::TemplateSource;
+use super::cache::CacheEntry;
+use super::cache::HttpCache;
+use super::relative_path;
+
+pub struct HttpSource {
+	base: Url,
+	client: reqwest::Client,
+	cache: Option<HttpCache>,
+	authenticated: bool,
+	// Only warn about being offline once, rather than once per file
+	warned_offline: AtomicBool,
+}
+
+impl HttpSource {
+	pub fn new(mut base: Url) -> Self {
+		// Without a trailing slash, `join` would replace the last segment of the path
+		// rather than appending to it.
+		if !base.path().ends_with('/') {
+			base.set_path(&format!("{}/", base.path()));
+		}
+		HttpSource {
+			base,
+			client: reqwest::Client::new(),
+			cache: None,
+			authenticated: false,
+			warned_offline: AtomicBool::new(false),
+		}
+	}
+
+	/// Whether a source at `base` can be pinned to a ref, which is only possible
+	/// on GitHub, where the ref is part of the path of every file.
+	pub fn can_be_pinned(base: &Url) -> bool {
+		base.host_str() == Some("raw.githubusercontent.com")
+	}
+
+	/// Pins the source to a branch, tag, or commit. Only works for GitHub, where
+	/// the ref is part of the path of every file.
+	pub fn with_ref(mut self, git_ref: &str) -> anyhow::Result<Self> {
+		let base = &self.base;
+		if !Self::can_be_pinned(base) {
+			return Err(anyhow!(
+				"{} can't be pinned to a ref, only GitHub and git sources can",
+				base
+			));
+		}
+		if git_ref.is_empty() {
+			return Err(anyhow!("ref must not be empty"));
+		}
+
+		// The path is always /<owner>/<repo>/<ref>/<path...>
+		let mut segments = base
+			.path_segments()
+			.map(|segments| segments.collect::<Vec<_>>())
+			.unwrap_or_default();
+		if segments.len() < 3 {
+			return Err(anyhow!("{} doesn't include a ref to replace", base));
+		}
+		segments[2] = git_ref;
+		let path = segments.join("/");
+		self.base.set_path(&path);
+		Ok(self)
+	}
+
+	/// Sends `headers` with every request, usually to authenticate.
+	pub fn with_headers(
+		mut self,
+		headers: header::HeaderMap,
+	) -> anyhow::Result<Self> {
+		self.authenticated = !headers.is_empty();
+		self.client =
+			reqwest::Client::builder().default_headers(headers).build()?;
+		Ok(self)
+	}
+
+	pub fn with_cache(mut self, cache: HttpCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
+
+	fn url(&self, path: &str, tag: Option<&str>) -> anyhow::Result<Url> {
+		Ok(self.base.join(&relative_path(path, tag))?)
+	}
+}
+
+/// Like `error_for_status`, but explains how to fix authentication errors.
+/// `authenticated` is whether the request was sent with any credentials.
+pub fn check_status(
+	response: reqwest::Response,
+	authenticated: bool,
+) -> anyhow::Result<reqwest::Response> {
+	let status = response.status();
+	let url = response.url();
+	match (status, authenticated) {
+		(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN, false) => Err(anyhow!(
+			"{} requires authentication ({}), try setting `token_env` for this source in bai.toml",
+			url,
+			status,
+		)),
+		(StatusCode::UNAUTHORIZED, true) => Err(anyhow!(
+			"{} rejected the credentials configured for this source ({}), check that the token is correct and hasn't expired",
+			url,
+			status,
+		)),
+		(StatusCode::FORBIDDEN, true) => Err(anyhow!(
+			"{} denied access with the credentials configured for this source ({}), check that the token has permission to read it",
+			url,
+			status,
+		)),
+		_ => Ok(response.error_for_status()?),
+	}
+}
+
+#[async_trait]
+impl TemplateSource for HttpSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		let url = self.url(path, tag)?;
+		let Some(cache) = &self.cache else {
+			let response = self.client.get(url).send().await?;
+			if response.status() == StatusCode::NOT_FOUND {
+				return Ok(None);
+			}
+			return Ok(Some(
+				check_status(response, self.authenticated)?.text().await?,
+			));
+		};
+
+		// Ask the server to only send the file if it's changed since we cached it
+		let cached = cache.load(&url);
+		let mut request = self.client.get(url.clone());
+		if let Some(cached) = &cached {
+			if let Some(etag) = &cached.etag {
+				request = request.header(header::IF_NONE_MATCH, etag);
+			}
+			if let Some(last_modified) = &cached.last_modified {
+				request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+			}
+		}
+
+		let response = match request.send().await {
+			Ok(response) => response,
+			Err(err) => {
+				let Some(cached) = cached else {
+					return Err(err.into());
+				};
+				if !self.warned_offline.swap(true, Ordering::Relaxed) {
+					eprintln!(
+						"{} unable to reach {}, using cached templates",
+						"warning:".yellow(),
+						self.base,
+					);
+				}
+				return Ok(Some(cached.body));
+			}
+		};
+
+		match response.status() {
+			StatusCode::NOT_MODIFIED if cached.is_some() => {
+				Ok(cached.map(|cached| cached.body))
+			}
+			StatusCode::NOT_FOUND => {
+				cache.remove(&url)?;
+				Ok(None)
+			}
+			_ => {
+				let response = check_status(response, self.authenticated)?;
+				let header = |name| {
+					response
+						.headers()
+						.get(name)
+						.and_then(|value: &header::HeaderValue| value.to_str().ok())
+						.map(|value| value.to_string())
+				};
+				let etag = header(header::ETAG);
+				let last_modified = header(header::LAST_MODIFIED);
+				let body = response.text().await?;
+
+				// Failing to update the cache shouldn't stop us from using the file
+				let entry = CacheEntry { etag, last_modified, body };
+				if let Err(err) = cache.store(&url, &entry) {
+					eprintln!("{} failed to cache {}: {}", "warning:".yellow(), url, err);
+				}
+				Ok(Some(entry.body))
+			}
+		}
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		Err(anyhow!("{} does not support listing templates", self.base))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn template_urls() {
+		let source =
+			HttpSource::new(Url::parse("https://example.com/templates").unwrap());
+		assert_eq!(
+			source.url("LICENSE", Some("mpl")).unwrap().as_str(),
+			"https://example.com/templates/@mpl/LICENSE",
+		);
+		assert_eq!(
+			source.url("src/main.rs", None).unwrap().as_str(),
+			"https://example.com/templates/src/main.rs",
+		);
+	}
+
+	#[test]
+	fn pinned_template_urls() {
+		let base = Url::parse(crate::source::DEFAULT_SOURCE).unwrap();
+		let source = HttpSource::new(base.clone()).with_ref("v1.0.3").unwrap();
+		assert_eq!(
+			source.url("LICENSE", Some("mpl")).unwrap().as_str(),
+			"https://raw.githubusercontent.com/aslilac/bai/v1.0.3/templates/@mpl/LICENSE",
+		);
+		let legacy = Url::parse(crate::source::LEGACY_SOURCE).unwrap();
+		let source = HttpSource::new(legacy).with_ref("v1.0.3").unwrap();
+		assert_eq!(
+			source.url("LICENSE", Some("mpl")).unwrap().as_str(),
+			"https://raw.githubusercontent.com/aslilac/bai/v1.0.3/static/@mpl/LICENSE",
+		);
+
+		assert!(HttpSource::new(base).with_ref("").is_err());
+		let base = Url::parse("https://example.com/templates/").unwrap();
+		assert!(HttpSource::new(base).with_ref("v1.0.3").is_err());
+	}
+}
@@ -0,0 +1,114 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use super::TemplateSource;
+
+/// Several sources, in order of priority. Each template is fetched from the
+/// first source that has it, so that a handful of files can be overridden
+/// without having to copy the rest of the collection. If a source can't be
+/// reached, the next one is tried instead.
+pub struct LayeredSource {
+	sources: Vec<Box<dyn TemplateSource>>,
+	// Only warn about each source being unreachable once, rather than once per file
+	warned: Vec<AtomicBool>,
+}
+
+impl LayeredSource {
+	pub fn new(sources: Vec<Box<dyn TemplateSource>>) -> Self {
+		let warned = sources.iter().map(|_| AtomicBool::new(false)).collect();
+		LayeredSource { sources, warned }
+	}
+}
+
+#[async_trait]
+impl TemplateSource for LayeredSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		let mut first_err = None;
+		for (source, warned) in self.sources.iter().zip(&self.warned) {
+			match source.fetch(path, tag).await {
+				Ok(Some(content)) => return Ok(Some(content)),
+				Ok(None) => continue,
+				Err(err) => {
+					if !warned.swap(true, Ordering::Relaxed) {
+						eprintln!(
+							"{} {}, trying the next source",
+							"warning:".yellow(),
+							err
+						);
+					}
+					first_err.get_or_insert(err);
+				}
+			}
+		}
+
+		match first_err {
+			Some(err) => Err(err),
+			None => Ok(None),
+		}
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		let mut files = BTreeSet::new();
+		let mut last_err = None;
+		for source in &self.sources {
+			// Not every source can be listed, but we should still list the ones that
+			// can be.
+			match source.list().await {
+				Ok(list) => files.extend(list),
+				Err(err) => last_err = Some(err),
+			}
+		}
+
+		match last_err {
+			Some(err) if files.is_empty() => Err(err),
+			_ if files.is_empty() && self.sources.is_empty() => {
+				Err(anyhow!("no template sources are configured"))
+			}
+			_ => Ok(files.into_iter().collect()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::EmbeddedSource;
+	use crate::source::LocalSource;
+
+	#[tokio::test]
+	async fn fetch_from_first_source_with_template() {
+		let source = LayeredSource::new(vec![
+			Box::new(EmbeddedSource::new(&[("LICENSE", "All rights reserved\n")])),
+			Box::new(LocalSource::new("templates")),
+		]);
+
+		let license = source.fetch("LICENSE", None).await.unwrap();
+		assert_eq!(license.as_deref(), Some("All rights reserved\n"));
+		let readme = source.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# {{ name }}\n"));
+		assert!(source.fetch("LICENSE", Some("nope")).await.unwrap().is_none());
+
+		let files = source.list().await.unwrap();
+		assert_eq!(files.iter().filter(|file| *file == "LICENSE").count(), 1);
+	}
+
+	#[tokio::test]
+	async fn fetch_from_next_source_on_error() {
+		let source = LayeredSource::new(vec![
+			Box::new(LocalSource::new("Cargo.toml")),
+			Box::new(LocalSource::new("templates")),
+		]);
+
+		let readme = source.fetch("README.md", None).await.unwrap();
+		assert_eq!(readme.as_deref(), Some("# {{ name }}\n"));
+		assert!(source.fetch("nope", None).await.is_err());
+	}
+}
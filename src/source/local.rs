@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::front_matter;
+
+use super::TemplateSource;
+use super::relative_path;
+use super::template_name;
+
+pub struct LocalSource {
+	root: PathBuf,
+}
+
+impl LocalSource {
+	pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+		LocalSource { root: root.into() }
+	}
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			walk(root, &path, files)?;
+		} else if let Ok(relative_path) = path.strip_prefix(root) {
+			files.extend(template_name(relative_path));
+		}
+	}
+
+	Ok(())
+}
+
+#[async_trait]
+impl TemplateSource for LocalSource {
+	async fn fetch(
+		&self,
+		path: &str,
+		tag: Option<&str>,
+	) -> anyhow::Result<Option<String>> {
+		let relative_path = relative_path(path, tag);
+		if !front_matter::stays_inside(&relative_path) {
+			return Err(anyhow!("{} is outside of {}", path, self.root.display()));
+		}
+		let path = self.root.join(relative_path);
+		match fs::read_to_string(&path) {
+			Ok(content) => Ok(Some(content)),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(err) => Err(anyhow!("failed to read {}: {}", path.display(), err)),
+		}
+	}
+
+	async fn list(&self) -> anyhow::Result<Vec<String>> {
+		let mut files = Vec::new();
+		walk(&self.root, &self.root, &mut files).map_err(|err| {
+			anyhow!("failed to read {}: {}", self.root.display(), err)
+		})?;
+		files.sort();
+		Ok(files)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn fetch_local_templates() {
+		let source = LocalSource::new("templates");
+		let license = source.fetch("LICENSE", Some("mpl")).await.unwrap();
+		assert!(license.unwrap().starts_with("Mozilla Public License"));
+		assert!(source.fetch("LICENSE", Some("nope")).await.unwrap().is_none());
+		assert!(source.fetch("../Cargo.toml", None).await.is_err());
+		assert!(source.fetch("/etc/passwd", None).await.is_err());
+		assert!(source.fetch("LICENSE", Some("../..")).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn list_local_templates() {
+		let files = LocalSource::new("templates").list().await.unwrap();
+		assert!(files.contains(&"Cargo.toml".to_string()));
+		assert!(files.contains(&"LICENSE@mpl".to_string()));
+		assert!(files.contains(&".github/workflows/check.yml@rust".to_string()));
+		assert!(!files.iter().any(|file| file.starts_with("_partials/")));
+	}
+}
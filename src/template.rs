@@ -0,0 +1,801 @@
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::Context;
+use crate::expression;
+use crate::expression::Expression;
+use crate::filters::Filter;
+use crate::front_matter;
+use crate::parse_file_name;
+use crate::source::TemplateSource;
+use crate::value::Value;
+
+/// Anything that looks like a tag. Tags which turn out not to mean anything to
+/// us are left in the output as they are, and tags which are escaped with a
+/// backslash (`\{{ like this }}`) are always left as they are.
+static TAG: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"(?<escape>\\)?\{\{(?<inner>.*?)\}\}").unwrap());
+/// Everything between `{{#raw}}` and `{{/raw}}` is left as it is
+static RAW_START: Lazy<Regex> = Lazy::new(|| Regex::new("^ *#raw *$").unwrap());
+static RAW_END: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"\{\{ */raw *\}\}").unwrap());
+static BLOCK_START: Lazy<Regex> = Lazy::new(|| {
+	Regex::new("^ *#(?<keyword>if|unless|each) +(?<expression>.*?) *$").unwrap()
+});
+/// `{{> path@tag }}`, which includes another template
+static PARTIAL: Lazy<Regex> =
+	Lazy::new(|| Regex::new("^ *> *(?<name>[^ ]+) *$").unwrap());
+static BLOCK_ELSE: Lazy<Regex> =
+	Lazy::new(|| Regex::new("^ *else *$").unwrap());
+static BLOCK_END: Lazy<Regex> =
+	Lazy::new(|| Regex::new("^ */(if|unless|each) *$").unwrap());
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BlockKind {
+	If,
+	Unless,
+	Each,
+}
+
+impl BlockKind {
+	fn keyword(self) -> &'static str {
+		match self {
+			BlockKind::If => "if",
+			BlockKind::Unless => "unless",
+			BlockKind::Each => "each",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node<'a> {
+	Text(&'a str),
+	/// `{{ name ?? "default" | filter }}`, or any other expression. If it uses
+	/// variables which aren't set, the tag is left as it is.
+	Variable {
+		expression: Expression<'a>,
+		tag: &'a str,
+	},
+	/// `{{> name }}`. If the tag is on a line of its own, `indent` is set, and
+	/// every line of the partial is indented to match it.
+	Partial {
+		name: &'a str,
+		indent: Option<&'a str>,
+		tag: &'a str,
+	},
+	/// `{{#if name}} ... {{else}} ... {{/if}}`, and the same for `unless` and
+	/// `each`. For `each`, `otherwise` is used when the list is empty or unset.
+	Block {
+		kind: BlockKind,
+		condition: Expression<'a>,
+		then: Vec<Node<'a>>,
+		otherwise: Vec<Node<'a>>,
+	},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template<'a> {
+	nodes: Vec<Node<'a>>,
+}
+
+/// The templates which can be included with `{{> name }}`, by name
+pub type Partials<'a> = HashMap<&'a str, Template<'a>>;
+
+/// A block which has been opened, but not yet closed.
+struct OpenBlock<'a> {
+	kind: BlockKind,
+	condition: Expression<'a>,
+	/// The condition as it was written, for errors
+	source: &'a str,
+	line: usize,
+	then: Vec<Node<'a>>,
+	otherwise: Option<Vec<Node<'a>>>,
+}
+
+impl<'a> OpenBlock<'a> {
+	fn nodes(&mut self) -> &mut Vec<Node<'a>> {
+		self.otherwise.as_mut().unwrap_or(&mut self.then)
+	}
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+	text[..offset].matches('\n').count() + 1
+}
+
+/// If a block tag is the only thing on its line, the whole line is removed
+/// along with it, so that blocks don't leave blank lines in the output.
+fn standalone(text: &str, range: Range<usize>) -> Range<usize> {
+	let line_start = text[..range.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+	let line_end = text[range.end..]
+		.find('\n')
+		.map(|i| range.end + i + 1)
+		.unwrap_or(text.len());
+
+	if text[line_start..range.start].trim().is_empty()
+		&& text[range.end..line_end].trim().is_empty()
+	{
+		line_start..line_end
+	} else {
+		range
+	}
+}
+
+/// Parses the filters captured after a variable name, like `| snake | upper`.
+pub fn parse_filters(filters: &str) -> anyhow::Result<Vec<Filter>> {
+	filters.split('|').skip(1).map(|filter| filter.trim().parse()).collect()
+}
+
+pub fn parse(text: &str) -> anyhow::Result<Template<'_>> {
+	let mut nodes = Vec::new();
+	let mut blocks: Vec<OpenBlock> = Vec::new();
+	let mut i = 0;
+
+	for captures in TAG.captures_iter(text) {
+		let tag = captures.get(0).unwrap();
+		let inner = captures.name("inner").unwrap().as_str();
+		let escaped = captures.name("escape").is_some();
+		let is_block = !escaped
+			&& (BLOCK_START.is_match(inner)
+				|| BLOCK_ELSE.is_match(inner)
+				|| BLOCK_END.is_match(inner)
+				|| RAW_START.is_match(inner)
+				|| PARTIAL.is_match(inner));
+
+		let range =
+			if is_block { standalone(text, tag.range()) } else { tag.range() };
+		// A tag could start inside of the line removed by the last one
+		if range.start < i {
+			continue;
+		}
+
+		let current = match blocks.last_mut() {
+			Some(block) => block.nodes(),
+			None => &mut nodes,
+		};
+		if range.start > i {
+			current.push(Node::Text(&text[i..range.start]));
+		}
+		i = range.end;
+
+		if escaped {
+			// Drop the backslash, but leave the rest of the tag as it is
+			current.push(Node::Text(&tag.as_str()[1..]));
+		} else if RAW_START.is_match(inner) {
+			let end = RAW_END.find_at(text, range.end).ok_or_else(|| {
+				anyhow!(
+					"{{{{#raw}}}} on line {} is never closed",
+					line_of(text, tag.start())
+				)
+			})?;
+			let end = standalone(text, end.range());
+			if end.start > range.end {
+				current.push(Node::Text(&text[range.end..end.start]));
+			}
+			i = end.end;
+		} else if let Some(partial) = PARTIAL.captures(inner) {
+			current.push(Node::Partial {
+				name: partial.name("name").unwrap().as_str(),
+				indent: (range != tag.range()).then(|| &text[range.start..tag.start()]),
+				tag: tag.as_str(),
+			});
+		} else if let Some(block) = BLOCK_START.captures(inner) {
+			let kind = match &block["keyword"] {
+				"if" => BlockKind::If,
+				"unless" => BlockKind::Unless,
+				_ => BlockKind::Each,
+			};
+			let source = block.name("expression").unwrap().as_str();
+			let line = line_of(text, tag.start());
+			let condition = expression::parse(source)
+				.map_err(|err| anyhow!("{} on line {}", err, line))?
+				.ok_or_else(|| {
+					anyhow!("invalid expression \"{}\" on line {}", source, line)
+				})?;
+			blocks.push(OpenBlock {
+				kind,
+				condition,
+				source,
+				line,
+				then: Vec::new(),
+				otherwise: None,
+			});
+		} else if BLOCK_ELSE.is_match(inner) {
+			match blocks.last_mut() {
+				Some(block) if block.otherwise.is_none() => {
+					block.otherwise = Some(Vec::new());
+				}
+				_ => {
+					return Err(anyhow!(
+						"unexpected {{{{else}}}} on line {}",
+						line_of(text, tag.start())
+					));
+				}
+			}
+		} else if let Some(end) = BLOCK_END.captures(inner) {
+			let keyword = &end[1];
+			let Some(block) =
+				blocks.pop().filter(|block| block.kind.keyword() == keyword)
+			else {
+				return Err(anyhow!(
+					"unexpected {{{{/{}}}}} on line {}",
+					keyword,
+					line_of(text, tag.start())
+				));
+			};
+
+			let node = Node::Block {
+				kind: block.kind,
+				condition: block.condition,
+				then: block.then,
+				otherwise: block.otherwise.unwrap_or_default(),
+			};
+			match blocks.last_mut() {
+				Some(block) => block.nodes().push(node),
+				None => nodes.push(node),
+			}
+		} else if let Some(expression) =
+			expression::parse(inner).map_err(|err| {
+				anyhow!("{} on line {}", err, line_of(text, tag.start()))
+			})? {
+			current.push(Node::Variable { expression, tag: tag.as_str() });
+		} else {
+			current.push(Node::Text(tag.as_str()));
+		}
+	}
+
+	if let Some(block) = blocks.pop() {
+		return Err(anyhow!(
+			"{{{{#{} {}}}}} on line {} is never closed",
+			block.kind.keyword(),
+			block.source,
+			block.line
+		));
+	}
+
+	if i < text.len() {
+		nodes.push(Node::Text(&text[i..]));
+	}
+	Ok(Template { nodes })
+}
+
+/// The variables available while rendering, which are those from the context,
+/// along with those set by any `{{#each}}` blocks that we're inside of.
+enum Scope<'a> {
+	Context(&'a Context),
+	Each { parent: &'a Scope<'a>, item: &'a str, index: usize, len: usize },
+}
+
+impl Scope<'_> {
+	fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+		match *self {
+			Scope::Context(ctx) => ctx.get(name).map(Cow::Borrowed),
+			Scope::Each { parent, item, index, len } => {
+				let value = match name {
+					"this" => item.to_string(),
+					"@index" => index.to_string(),
+					"@first" => (index == 0).to_string(),
+					"@last" => (index + 1 == len).to_string(),
+					_ => return parent.get(name),
+				};
+				Some(Cow::Owned(Value::String(value)))
+			}
+		}
+	}
+
+	fn eval<'a>(
+		&self,
+		expression: &Expression<'a>,
+	) -> Result<Value, Vec<&'a str>> {
+		expression.eval(&|name| self.get(name).map(Cow::into_owned))
+	}
+}
+
+/// Variables which are unset, empty, `false`, or `0` are considered false by
+/// conditional blocks, as are empty lists.
+fn is_truthy(value: Option<&Value>) -> bool {
+	match value {
+		None => false,
+		Some(Value::String(value)) => !matches!(value.as_str(), "" | "false" | "0"),
+		Some(Value::List(items)) => !items.is_empty(),
+	}
+}
+
+fn render_nodes<'a>(
+	nodes: &[Node<'a>],
+	scope: &Scope,
+	partials: &Partials<'a>,
+	output: &mut Rendered<'a>,
+) {
+	for node in nodes {
+		match node {
+			Node::Text(text) => output.text.push_str(text),
+			Node::Variable { expression, tag } => match scope.eval(expression) {
+				Ok(value) => output.text.push_str(&value.to_string()),
+				Err(names) => {
+					output.text.push_str(tag);
+					for name in names {
+						if !output.unresolved.contains(&name) {
+							output.unresolved.push(name);
+						}
+					}
+				}
+			},
+			Node::Partial { name, indent, tag } => {
+				let Some(partial) = partials.get(name) else {
+					output.text.push_str(tag);
+					continue;
+				};
+				let Some(indent) = indent else {
+					render_nodes(&partial.nodes, scope, partials, output);
+					continue;
+				};
+
+				let mut rendered = Rendered::default();
+				render_nodes(&partial.nodes, scope, partials, &mut rendered);
+				for line in rendered.text.split_inclusive('\n') {
+					if !line.trim().is_empty() {
+						output.text.push_str(indent);
+					}
+					output.text.push_str(line);
+				}
+				// The tag's line was removed, including the line break at the end
+				if !rendered.text.is_empty() && !rendered.text.ends_with('\n') {
+					output.text.push('\n');
+				}
+				for name in rendered.unresolved {
+					if !output.unresolved.contains(&name) {
+						output.unresolved.push(name);
+					}
+				}
+			}
+			Node::Block { kind: BlockKind::Each, condition, then, otherwise } => {
+				let value = scope.eval(condition).ok();
+				let items = value.as_ref().map(Value::items).unwrap_or_default();
+				if items.is_empty() {
+					render_nodes(otherwise, scope, partials, output);
+				}
+				for (index, item) in items.iter().enumerate() {
+					let scope =
+						Scope::Each { parent: scope, item, index, len: items.len() };
+					render_nodes(then, &scope, partials, output);
+				}
+			}
+			Node::Block { kind, condition, then, otherwise } => {
+				let negated = *kind == BlockKind::Unless;
+				if is_truthy(scope.eval(condition).ok().as_ref()) != negated {
+					render_nodes(then, scope, partials, output);
+				} else {
+					render_nodes(otherwise, scope, partials, output);
+				}
+			}
+		}
+	}
+}
+
+/// The output of a template, along with the names of any variables which
+/// weren't set, and so were left in the output as they were.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rendered<'a> {
+	pub text: String,
+	pub unresolved: Vec<&'a str>,
+}
+
+impl<'a> Template<'a> {
+	pub fn render(&self, ctx: &Context, partials: &Partials<'a>) -> Rendered<'a> {
+		let mut output = Rendered::default();
+		render_nodes(&self.nodes, &Scope::Context(ctx), partials, &mut output);
+		output
+	}
+
+	/// The names of every partial included by the template, but not by those
+	/// partials.
+	fn partials(&self) -> Vec<&'a str> {
+		fn collect<'a>(nodes: &[Node<'a>], names: &mut Vec<&'a str>) {
+			for node in nodes {
+				match node {
+					Node::Partial { name, .. } => names.push(name),
+					Node::Block { then, otherwise, .. } => {
+						collect(then, names);
+						collect(otherwise, names);
+					}
+					_ => (),
+				}
+			}
+		}
+
+		let mut names = Vec::new();
+		collect(&self.nodes, &mut names);
+		names
+	}
+
+	/// The variables used by the template, as those which need to be set for it
+	/// to render completely, and those which it can do without, because they're
+	/// only used by blocks or have a default.
+	pub fn variables(&self) -> (Vec<&'a str>, Vec<&'a str>) {
+		fn collect<'a>(
+			nodes: &[Node<'a>],
+			required: &mut Vec<&'a str>,
+			optional: &mut Vec<&'a str>,
+		) {
+			for node in nodes {
+				match node {
+					Node::Variable { expression, .. } => {
+						expression.variables(required, optional);
+					}
+					Node::Block { condition, then, otherwise, .. } => {
+						condition.optional_variables(optional);
+						collect(then, required, optional);
+						collect(otherwise, required, optional);
+					}
+					_ => (),
+				}
+			}
+		}
+
+		let mut required = Vec::new();
+		let mut optional = Vec::new();
+		collect(&self.nodes, &mut required, &mut optional);
+		(required, optional)
+	}
+
+	/// Variables which have some text as a default, along with that text, which
+	/// is a good suggestion when asking for them.
+	pub fn defaults(&self) -> Vec<(&'a str, &'a str)> {
+		fn collect<'a>(nodes: &[Node<'a>], defaults: &mut Vec<(&'a str, &'a str)>) {
+			for node in nodes {
+				match node {
+					Node::Variable { expression, .. } => expression.defaults(defaults),
+					Node::Block { condition, then, otherwise, .. } => {
+						condition.defaults(defaults);
+						collect(then, defaults);
+						collect(otherwise, defaults);
+					}
+					_ => (),
+				}
+			}
+		}
+
+		let mut defaults = Vec::new();
+		collect(&self.nodes, &mut defaults);
+		defaults
+	}
+}
+
+/// Fetches every partial that a template includes, directly or through other
+/// partials, so that they're ready before it's rendered. Partials are named
+/// just like any other file, as `path@tag`.
+pub async fn fetch_partials(
+	source: &dyn TemplateSource,
+	template: &Template<'_>,
+) -> anyhow::Result<HashMap<String, String>> {
+	let mut texts = HashMap::new();
+	let mut queue =
+		template.partials().into_iter().map(String::from).collect::<Vec<_>>();
+
+	while let Some(name) = queue.pop() {
+		if texts.contains_key(&name) {
+			continue;
+		}
+		let (path, tag) = parse_file_name(&name);
+		if !front_matter::stays_inside(path) {
+			return Err(anyhow!(
+				"partial {} is outside of the template source",
+				name
+			));
+		}
+		let text = source
+			.fetch(path, tag.as_deref())
+			.await?
+			.ok_or_else(|| anyhow!("partial not found: {}", name))?;
+		// Partials can have front matter too, but it doesn't mean anything
+		let (_, text) =
+			front_matter::split(&text).map_err(|err| anyhow!("{}: {}", name, err))?;
+		let partial = parse(text).map_err(|err| anyhow!("{}: {}", name, err))?;
+		queue.extend(partial.partials().into_iter().map(String::from));
+		texts.insert(name, text.to_string());
+	}
+
+	Ok(texts)
+}
+
+/// Parses the partials returned by `fetch_partials`, and makes sure that none
+/// of them include themselves, which would never finish rendering.
+pub fn parse_partials(
+	texts: &HashMap<String, String>,
+) -> anyhow::Result<Partials<'_>> {
+	let partials = texts
+		.iter()
+		.map(|(name, text)| Ok((name.as_str(), parse(text)?)))
+		.collect::<anyhow::Result<Partials>>()?;
+
+	fn visit<'a>(
+		name: &'a str,
+		partials: &Partials<'a>,
+		stack: &mut Vec<&'a str>,
+	) -> anyhow::Result<()> {
+		if let Some(start) = stack.iter().position(|included| *included == name) {
+			return Err(anyhow!(
+				"partials include each other in a cycle: {} -> {}",
+				stack[start..].join(" -> "),
+				name
+			));
+		}
+		stack.push(name);
+		for included in partials[name].partials() {
+			visit(included, partials, stack)?;
+		}
+		stack.pop();
+		Ok(())
+	}
+
+	for name in partials.keys() {
+		visit(name, &partials, &mut Vec::new())?;
+	}
+	Ok(partials)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn render(text: &str, ctx: &[(&str, &str)]) -> String {
+		let ctx = ctx
+			.iter()
+			.map(|(key, value)| (key.to_string(), Value::from(*value)))
+			.collect();
+		parse(text).unwrap().render(&ctx, &Partials::new()).text
+	}
+
+	fn render_list(text: &str, name: &str, items: &[&str]) -> String {
+		let items = items.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+		let ctx = Context::from([(name.to_string(), Value::from(items))]);
+		parse(text).unwrap().render(&ctx, &Partials::new()).text
+	}
+
+	#[test]
+	fn variables() {
+		assert_eq!(render("# {{ name }}\n", &[("name", "bai")]), "# bai\n");
+		// Unset variables, and anything else that looks like a tag, are left alone
+		assert_eq!(render("# {{ name }}\n", &[]), "# {{ name }}\n");
+		assert_eq!(
+			render("${{ github.event_name == 'pull_request' }}", &[]),
+			"${{ github.event_name == 'pull_request' }}",
+		);
+	}
+
+	#[test]
+	fn defaults() {
+		let text = r#"{{ author.homepage ?? "example.com" }}"#;
+		assert_eq!(render(text, &[("author.homepage", "mckay.la")]), "mckay.la");
+		assert_eq!(render(text, &[]), "example.com");
+		// Only unset variables are replaced, not empty ones
+		assert_eq!(render(text, &[("author.homepage", "")]), "");
+
+		let text = r#"{{ license.owner ?? author.name ?? "?" | upper }}"#;
+		assert_eq!(render(text, &[("author.name", "James")]), "JAMES");
+		assert_eq!(render(text, &[]), "?");
+		assert_eq!(render("{{ a ?? b }}", &[]), "{{ a ?? b }}");
+	}
+
+	#[test]
+	fn unresolved_variables() {
+		let template =
+			parse("{{ a }} {{ b ?? c }} {{ d ?? \"d\" }} {{ a }} {{#if e}}{{/if}}")
+				.unwrap();
+		let ctx = Context::new();
+		assert_eq!(template.render(&ctx, &Partials::new()).unresolved, ["a", "b"]);
+	}
+
+	#[test]
+	fn filters() {
+		let ctx = [("name", "my-coolProject")];
+		assert_eq!(render("{{ name | snake }}", &ctx), "my_cool_project");
+		assert_eq!(render("{{name|pascal|upper}}", &ctx), "MYCOOLPROJECT");
+		assert_eq!(render("{{ name | kebab }}", &[]), "{{ name | kebab }}");
+		assert_eq!(render_list("{{ xs | upper }}", "xs", &["a", "b"]), "A, B",);
+		assert_eq!(
+			render("${{ github.head_ref || github.ref }}", &ctx),
+			"${{ github.head_ref || github.ref }}",
+		);
+		assert_eq!(render("{{ name | reverse }}", &ctx), "{{ name | reverse }}");
+		assert_eq!(
+			render("- debug: {{ item | default }}", &ctx),
+			"- debug: {{ item | default }}",
+		);
+	}
+
+	#[test]
+	fn conditionals() {
+		let text = "a{{#if x}}b{{else}}c{{/if}}d";
+		assert_eq!(render(text, &[("x", "yes")]), "abd");
+		assert_eq!(render(text, &[]), "acd");
+		assert_eq!(render(text, &[("x", "")]), "acd");
+		assert_eq!(render(text, &[("x", "false")]), "acd");
+		assert_eq!(render(text, &[("x", "0")]), "acd");
+
+		let text = "a{{#unless x}}b{{/unless}}c";
+		assert_eq!(render(text, &[("x", "yes")]), "ac");
+		assert_eq!(render(text, &[]), "abc");
+
+		let text = "{{#if a}}{{#if b}}ab{{else}}a{{/if}}{{/if}}";
+		assert_eq!(render(text, &[("a", "1"), ("b", "1")]), "ab");
+		assert_eq!(render(text, &[("a", "1")]), "a");
+		assert_eq!(render(text, &[("b", "1")]), "");
+	}
+
+	#[test]
+	fn expressions() {
+		let ctx =
+			[("github.owner", "aslilac"), ("name", "bai"), ("license", "MIT")];
+		assert_eq!(
+			render(r#"{{ github.owner + "/" + name }}"#, &ctx),
+			"aslilac/bai"
+		);
+		assert_eq!(render(r#"{{ (name | upper) + "-cli" }}"#, &ctx), "BAI-cli");
+
+		let text = r#"{{#if license == "MIT"}}mit{{else}}other{{/if}}"#;
+		assert_eq!(render(text, &ctx), "mit");
+		assert_eq!(render(text, &[("license", "MPL-2.0")]), "other");
+		assert_eq!(render(text, &[]), "other");
+		let text = r#"{{#unless license != "MIT"}}mit{{/unless}}"#;
+		assert_eq!(render(text, &ctx), "mit");
+
+		let text = r#"{{ github.owner + "/" + repo }}"#;
+		let template = parse(text).unwrap();
+		let ctx = Context::from([("github.owner".to_string(), Value::from("a"))]);
+		let rendered = template.render(&ctx, &Partials::new());
+		assert_eq!(rendered.text, text);
+		assert_eq!(rendered.unresolved, ["repo"]);
+
+		assert!(parse("{{#if name ==}}{{/if}}").is_err());
+		assert!(parse("{{#if (name}}{{/if}}").is_err());
+	}
+
+	#[test]
+	fn loops() {
+		let text = "[{{#each keywords}}\"{{ this }}\"{{#unless @last}}, {{/unless}}{{/each}}]";
+		assert_eq!(
+			render_list(text, "keywords", &["cli", "templates"]),
+			"[\"cli\", \"templates\"]"
+		);
+		assert_eq!(render_list(text, "keywords", &[]), "[]");
+		assert_eq!(render(text, &[("keywords", "cli")]), "[\"cli\"]");
+		assert_eq!(render(text, &[]), "[]");
+
+		let text = "{{#each xs}}{{ @index }}{{ this }}{{else}}none{{/each}}";
+		assert_eq!(render_list(text, "xs", &["a", "b"]), "0a1b");
+		assert_eq!(render_list(text, "xs", &[]), "none");
+		assert_eq!(render_list("{{ xs }}", "xs", &["a", "b"]), "a, b");
+
+		// Outside of a loop, loop variables are left alone
+		assert_eq!(
+			render("{{ this }} {{ @index }}", &[]),
+			"{{ this }} {{ @index }}"
+		);
+	}
+
+	#[test]
+	fn escapes() {
+		let ctx = [("github.owner", "aslilac"), ("name", "bai")];
+		assert_eq!(
+			render("owner: $\\{{ github.owner }} {{ github.owner }}", &ctx),
+			"owner: ${{ github.owner }} aslilac",
+		);
+		assert_eq!(render("\\{{#if x}}", &ctx), "{{#if x}}");
+
+		let text = "\
+{{ name }}
+{{#raw}}
+- run: echo ${{ github.owner }} {{ name }}
+  if: {{#if x}}
+{{/raw}}
+{{ name }}
+";
+		assert_eq!(
+			render(text, &ctx),
+			"bai\n- run: echo ${{ github.owner }} {{ name }}\n  if: {{#if x}}\nbai\n",
+		);
+		assert!(parse("{{#raw}} {{ name }}").is_err());
+
+		// Escaped and raw variables aren't unresolved, because they're not variables
+		let template = parse("\\{{ a }} {{#raw}}{{ b }}{{/raw}}").unwrap();
+		assert!(
+			template.render(&Context::new(), &Partials::new()).unresolved.is_empty()
+		);
+	}
+
+	#[test]
+	fn required_variables() {
+		let template = parse(
+			r#"{{ a }} {{ b ?? c }} {{ d ?? e ?? "f" }}{{#if g}}{{ h }}{{/if}}"#,
+		)
+		.unwrap();
+		assert_eq!(
+			template.variables(),
+			(vec!["a", "c", "h"], vec!["b", "d", "e", "g"])
+		);
+		assert_eq!(template.defaults(), [("d", "f"), ("e", "f")]);
+	}
+
+	#[test]
+	fn partials() {
+		let texts = HashMap::from([
+			(
+				"checkout.yml".to_string(),
+				"- uses: actions/checkout@v4\n  name: {{ step ?? \"Checkout\" }}\n"
+					.to_string(),
+			),
+			("a".to_string(), "a{{> b }}".to_string()),
+			("b".to_string(), "b{{#if x}}{{> a }}{{/if}}".to_string()),
+		]);
+		assert!(parse_partials(&texts).is_err());
+
+		let texts = HashMap::from([(
+			"checkout.yml".to_string(),
+			texts["checkout.yml"].clone(),
+		)]);
+		let partials = parse_partials(&texts).unwrap();
+		let template =
+			parse("steps:\n  {{> checkout.yml }}\n  - run: test\n").unwrap();
+		assert_eq!(template.partials(), ["checkout.yml"]);
+		assert_eq!(
+			template.render(&Context::new(), &partials).text,
+			"steps:\n  - uses: actions/checkout@v4\n    name: Checkout\n  - run: test\n",
+		);
+		let template = parse("[{{> checkout.yml }}] {{> missing }}").unwrap();
+		assert_eq!(
+			template.render(&Context::new(), &partials).text,
+			"[- uses: actions/checkout@v4\n  name: Checkout\n] {{> missing }}",
+		);
+	}
+
+	#[tokio::test]
+	async fn partials_stay_inside_source() {
+		let source = crate::source::LocalSource::new("templates");
+		let template = parse("{{> _partials/github/checkout.yml }}").unwrap();
+		let texts = fetch_partials(&source, &template).await.unwrap();
+		assert!(texts.contains_key("_partials/github/checkout.yml"));
+
+		for name in ["../Cargo.toml", "/etc/passwd", "_partials/../../Cargo.toml"] {
+			let text = format!("{{{{> {name} }}}}");
+			let template = parse(&text).unwrap();
+			assert!(fetch_partials(&source, &template).await.is_err());
+		}
+	}
+
+	#[test]
+	fn standalone_blocks() {
+		let text = "\
+[package]
+name = \"{{ name }}\"
+{{#if author.homepage}}
+homepage = \"https://{{ author.homepage }}/{{ name }}\"
+	{{ else }}
+# no homepage
+{{/if}}
+edition = \"2024\"
+";
+		assert_eq!(
+			render(text, &[("name", "bai"), ("author.homepage", "mckay.la")]),
+			"[package]\nname = \"bai\"\nhomepage = \"https://mckay.la/bai\"\nedition = \"2024\"\n",
+		);
+		assert_eq!(
+			render(text, &[("name", "bai")]),
+			"[package]\nname = \"bai\"\n# no homepage\nedition = \"2024\"\n",
+		);
+	}
+
+	#[test]
+	fn invalid_blocks() {
+		assert!(parse("{{#if x}}").is_err());
+		assert!(parse("{{/if}}").is_err());
+		assert!(parse("{{else}}").is_err());
+		assert!(parse("{{#if x}}{{/unless}}").is_err());
+		assert!(parse("{{#each x}}{{/if}}").is_err());
+		assert!(parse("{{#if x}}{{else}}{{else}}{{/if}}").is_err());
+	}
+}
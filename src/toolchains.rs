@@ -0,0 +1,71 @@
+use std::process::Command;
+
+use crate::Context;
+
+/// Variables which can be filled in by asking a toolchain for its version,
+/// along with the command to ask it with.
+static TOOLCHAINS: &[(&str, &[&str])] = &[
+	("go.version", &["go", "version"]),
+	("rust.version", &["rustc", "--version"]),
+	("node.version", &["node", "--version"]),
+	("gleam.version", &["gleam", "--version"]),
+];
+
+/// Finds the version number in the output of a toolchain's version command,
+/// like `go version go1.22.3 linux/amd64`, `rustc 1.85.0 (4d91de4e4 2025-02-17)`,
+/// or `v22.3.0`.
+fn parse_version(output: &str) -> Option<&str> {
+	output.split_whitespace().find_map(|word| {
+		let version = word
+			.strip_prefix("go")
+			.or_else(|| word.strip_prefix('v'))
+			.unwrap_or(word);
+		let is_version = version.starts_with(|c: char| c.is_ascii_digit())
+			&& version.contains('.');
+		is_version.then_some(version)
+	})
+}
+
+/// Sets the versions of any toolchains in `names` which aren't already set, by
+/// running them. Toolchains which aren't installed are left unset. Returns
+/// whether any versions were set.
+pub fn detect(ctx: &mut Context, names: &[String]) -> bool {
+	let mut detected = false;
+	for (variable, command) in TOOLCHAINS {
+		if ctx.contains_key(*variable) || !names.iter().any(|name| name == variable)
+		{
+			continue;
+		}
+
+		let output = Command::new(command[0]).args(&command[1..]).output();
+		if let Ok(output) = output
+			&& output.status.success()
+			&& let Some(version) =
+				parse_version(&String::from_utf8_lossy(&output.stdout))
+		{
+			ctx.insert(variable.to_string(), version.into());
+			detected = true;
+		}
+	}
+	detected
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_versions() {
+		assert_eq!(
+			parse_version("go version go1.22.3 linux/amd64\n"),
+			Some("1.22.3")
+		);
+		assert_eq!(
+			parse_version("rustc 1.85.0 (4d91de4e4 2025-02-17)\n"),
+			Some("1.85.0")
+		);
+		assert_eq!(parse_version("v22.3.0\n"), Some("22.3.0"));
+		assert_eq!(parse_version("gleam 1.4.1\n"), Some("1.4.1"));
+		assert_eq!(parse_version("command not found"), None);
+	}
+}
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+
+/// The value of a context variable, which is usually a string, but can also be
+/// a list, for things like keywords or workspace members.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Value {
+	String(String),
+	List(Vec<String>),
+}
+
+impl Value {
+	/// The items to loop over with `{{#each}}`. A string is treated as a list
+	/// with just one item.
+	pub fn items(&self) -> &[String] {
+		match self {
+			Value::String(value) => std::slice::from_ref(value),
+			Value::List(items) => items,
+		}
+	}
+
+	/// Adds an item to the value, turning it into a list if it isn't one already.
+	pub fn push(&mut self, item: String) {
+		match self {
+			Value::String(value) => {
+				*self = Value::List(vec![std::mem::take(value), item]);
+			}
+			Value::List(items) => items.push(item),
+		}
+	}
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self {
+		Value::String(value)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self {
+		Value::String(value.to_string())
+	}
+}
+
+impl From<Vec<String>> for Value {
+	fn from(items: Vec<String>) -> Self {
+		Value::List(items)
+	}
+}
+
+/// Lists are written out separated by commas when used as a plain variable.
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Value::String(value) => f.write_str(value),
+			Value::List(items) => f.write_str(&items.join(", ")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn push_items() {
+		let mut value = Value::from("a");
+		assert_eq!(value.items(), ["a"]);
+		value.push("b".to_string());
+		value.push("c".to_string());
+		assert_eq!(value, Value::from(vec!["a".into(), "b".into(), "c".into()]));
+		assert_eq!(value.to_string(), "a, b, c");
+	}
+}
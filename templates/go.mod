@@ -0,0 +1,3 @@
+module github.com/{{ github.owner }}/{{ name }}
+
+go {{ go.version }}
@@ -12,6 +12,13 @@ static EXE: LazyLock<PathBuf> = LazyLock::new(|| {
 		.expect("unable to canonicalize path")
 });
 
+// Use the templates from this checkout, rather than fetching them from GitHub,
+// so that the tests don't depend on the network, and so that changes to the
+// templates can be tested before they're pushed.
+static SOURCE: LazyLock<PathBuf> = LazyLock::new(|| {
+	Path::new("./templates/").canonicalize().expect("unable to canonicalize path")
+});
+
 static DEFAULT_DEFINES: &[&str] = &[
 	"-d",
 	"git.branch=trunk",
@@ -37,6 +44,8 @@ fn new_gleam_project() {
 
 	let result = Command::new(&*EXE)
 		.args(DEFAULT_DEFINES)
+		.arg("-source")
+		.arg(&*SOURCE)
 		.arg("/gleam")
 		.current_dir(PATH)
 		.output()
@@ -78,6 +87,8 @@ fn new_react_project() {
 
 	let result = Command::new(&*EXE)
 		.args(DEFAULT_DEFINES)
+		.arg("-source")
+		.arg(&*SOURCE)
 		.arg("/react")
 		.current_dir(PATH)
 		.output()
@@ -99,6 +110,8 @@ fn new_typescript_project() {
 
 	let result = Command::new(&*EXE)
 		.args(DEFAULT_DEFINES)
+		.arg("-source")
+		.arg(&*SOURCE)
 		.arg("/ts")
 		.current_dir(PATH)
 		.output()
@@ -120,6 +133,8 @@ fn new_rust_project() {
 
 	let result = Command::new(&*EXE)
 		.args(DEFAULT_DEFINES)
+		.arg("-source")
+		.arg(&*SOURCE)
 		.arg("/rust")
 		.current_dir(PATH)
 		.output()